@@ -0,0 +1,14 @@
+#version 450
+
+layout(location=0) out vec2 v_tex_coords;
+
+// A single triangle that covers the whole screen, drawn without a vertex buffer
+void main() {
+    vec2 position = vec2(
+        gl_VertexIndex == 1 ? 3.0 : -1.0,
+        gl_VertexIndex == 2 ? 3.0 : -1.0
+    );
+    // Clip space y points up, texture v points down
+    v_tex_coords = vec2(position.x + 1.0, 1.0 - position.y) * 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -5,16 +5,8 @@ layout(location=1) in vec2 a_tex_coords;
 
 layout(location=0) out vec2 v_tex_coords;
 
-layout(set = 1, binding = 0) uniform Uniforms {
-    mat4 u_view;
-    mat4 u_proj;
-};
-
+// Overlay vertices are already in clip space
 void main() {
     v_tex_coords = a_tex_coords;
-    gl_Position = (u_proj * u_view) * vec4(a_position, 1.0);
+    gl_Position = vec4(a_position.xy, 0.0, 1.0);
 }
-
- 
-
- 
\ No newline at end of file
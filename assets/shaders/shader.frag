@@ -0,0 +1,62 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec3 v_normal;
+layout(location=2) in vec4 v_light_position;
+layout(location=0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform texture2D t_diffuse;
+layout(set = 0, binding = 1) uniform sampler s_diffuse;
+
+layout(set = 1, binding = 1) uniform Light {
+    vec3 u_light_direction;
+    vec3 u_light_color;
+    float u_ambient;
+};
+
+layout(set = 2, binding = 0) uniform texture2D t_shadow;
+layout(set = 2, binding = 1) uniform samplerShadow s_shadow;
+layout(set = 2, binding = 2) uniform Shadow {
+    mat4 u_light_view_proj;
+    float u_shadows_enabled;
+    int u_pcf_radius;
+    float u_shadow_texel_size;
+};
+
+// 1.0 when fully lit, 0.0 when fully in shadow
+float shadow() {
+    vec3 ndc = v_light_position.xyz / v_light_position.w;
+    // Clip space y points up, texture v points down
+    vec2 uv = ndc.xy * vec2(0.5, -0.5) + 0.5;
+
+    float lit = 0.0;
+    float samples = 0.0;
+    for (int x = -u_pcf_radius; x <= u_pcf_radius; x++) {
+        for (int y = -u_pcf_radius; y <= u_pcf_radius; y++) {
+            vec2 offset = vec2(x, y) * u_shadow_texel_size;
+            lit += texture(sampler2DShadow(t_shadow, s_shadow), vec3(uv + offset, ndc.z));
+            samples += 1.0;
+        }
+    }
+
+    // Anything outside the shadow map is lit
+    float inside = step(0.0, uv.x) * step(uv.x, 1.0) * step(0.0, uv.y) * step(uv.y, 1.0)
+        * step(ndc.z, 1.0);
+
+    return mix(1.0, lit / samples, inside * u_shadows_enabled);
+}
+
+void main() {
+    vec4 diffuse_color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords);
+
+#ifdef ALPHA_CUTOUT
+    if (diffuse_color.a < 0.5) {
+        discard;
+    }
+#endif
+
+    float diffuse = max(dot(normalize(v_normal), -normalize(u_light_direction)), 0.0);
+    vec3 light = u_light_color * (u_ambient + (1.0 - u_ambient) * diffuse * shadow());
+
+    f_color = vec4(diffuse_color.rgb * light, diffuse_color.a);
+}
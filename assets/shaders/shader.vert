@@ -0,0 +1,39 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec2 a_tex_coords;
+layout(location=2) in vec3 a_normal;
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) out vec3 v_normal;
+layout(location=2) out vec4 v_light_position;
+
+layout(set = 1, binding = 0) uniform Uniforms {
+    mat4 u_view;
+    mat4 u_proj;
+    float u_time;
+};
+
+layout(set = 2, binding = 2) uniform Shadow {
+    mat4 u_light_view_proj;
+    float u_shadows_enabled;
+    int u_pcf_radius;
+    float u_shadow_texel_size;
+};
+
+layout(set = 3, binding = 0) uniform Material {
+    float u_frame_count;
+    float u_frame_duration;
+    vec2 u_scroll;
+};
+
+void main() {
+    // Frames sit side by side, so each one covers 1 / u_frame_count of the texture's width
+    float frame = floor(mod(u_time / u_frame_duration, u_frame_count));
+    v_tex_coords = vec2((a_tex_coords.x + frame) / u_frame_count, a_tex_coords.y);
+    v_tex_coords += u_scroll * u_time;
+    // Vertices are already in world space so the normal needs no transform
+    v_normal = a_normal;
+    v_light_position = u_light_view_proj * vec4(a_position, 1.0);
+    gl_Position = (u_proj * u_view) * vec4(a_position, 1.0);
+}
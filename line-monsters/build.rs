@@ -3,6 +3,10 @@ use glob::glob;
 use std::fs::{read_to_string, write};
 use std::path::PathBuf;
 
+/// Shaders compiled a second time with a preprocessor define set, written out as
+/// `<name>.<define in lowercase>.<ext>.spv` next to the plain build
+const VARIANTS: &[(&str, &str)] = &[("shader.frag", "ALPHA_CUTOUT")];
+
 struct ShaderData {
     src: String,
     src_path: PathBuf,
@@ -37,14 +41,17 @@ impl ShaderData {
 }
 
 fn main() -> Result<()> {
-    // This tells cargo to rerun this script if something in /src/ changes.
-    println!("cargo:rerun-if-changed=src/res/*");
+    // The game loads the compiled shaders from the assets directory at runtime,
+    // so a running debug build picks up shaders rebuilt by this script.
+
+    // Watching the directory as well reruns this script when a shader is added
+    println!("cargo:rerun-if-changed=../assets/shaders");
 
-    // Collect all shaders recursively within /src/
+    // Collect all shaders recursively within /assets/shaders/
     let mut shader_paths = [
-        glob("./src/res/**/*.vert")?,
-        glob("./src/res/**/*.frag")?,
-        glob("./src/res/**/*.comp")?,
+        glob("../assets/shaders/**/*.vert")?,
+        glob("../assets/shaders/**/*.frag")?,
+        glob("../assets/shaders/**/*.comp")?,
     ];
 
     // This could be parallelized
@@ -64,6 +71,9 @@ fn main() -> Result<()> {
     // be better just to only compile shaders that have been changed
     // recently.
     for shader in shaders? {
+        // This tells cargo to rerun this script if the shader changes.
+        println!("cargo:rerun-if-changed={}", shader.src_path.display());
+
         let compiled = compiler.compile_into_spirv(
             &shader.src,
             shader.kind,
@@ -71,7 +81,32 @@ fn main() -> Result<()> {
             "main",
             None,
         )?;
-        write(shader.spv_path, compiled.as_binary_u8())?;
+        write(&shader.spv_path, compiled.as_binary_u8())?;
+
+        let file_name = shader.src_path.file_name().and_then(|name| name.to_str());
+        for (_, define) in VARIANTS.iter().filter(|(name, _)| Some(*name) == file_name) {
+            let mut options =
+                shaderc::CompileOptions::new().context("Unable to create compile options")?;
+            options.add_macro_definition(define, None);
+
+            let compiled = compiler.compile_into_spirv(
+                &shader.src,
+                shader.kind,
+                &shader.src_path.to_str().unwrap(),
+                "main",
+                Some(&options),
+            )?;
+
+            let stem = shader.src_path.file_stem().unwrap().to_str().unwrap();
+            let extension = shader.src_path.extension().unwrap().to_str().unwrap();
+            let variant_path = shader.src_path.with_file_name(format!(
+                "{}.{}.{}.spv",
+                stem,
+                define.to_lowercase(),
+                extension
+            ));
+            write(variant_path, compiled.as_binary_u8())?;
+        }
     }
 
     Ok(())
@@ -1,72 +1,26 @@
-use ultraviolet::{Mat4, Vec3};
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+use crate::input::{Action, Input};
+use ultraviolet::{Mat4, Vec3, Vec4};
 
 pub struct CameraController {
     speed: f32,
-    is_forward_pressed: bool,
-    is_backward_pressed: bool,
-    is_left_pressed: bool,
-    is_right_pressed: bool,
 }
 
 impl CameraController {
     pub fn new(speed: f32) -> Self {
-        Self {
-            speed,
-            is_forward_pressed: false,
-            is_backward_pressed: false,
-            is_left_pressed: false,
-            is_right_pressed: false,
-        }
+        Self { speed }
     }
 
-    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        state,
-                        virtual_keycode: Some(keycode),
-                        ..
-                    },
-                ..
-            } => {
-                let is_pressed = *state == ElementState::Pressed;
-                match keycode {
-                    VirtualKeyCode::W => {
-                        self.is_forward_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::A => {
-                        self.is_left_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::S => {
-                        self.is_backward_pressed = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::D => {
-                        self.is_right_pressed = is_pressed;
-                        true
-                    }
-                    _ => false,
-                }
-            }
-            _ => false,
-        }
-    }
-
-    pub fn update_camera(&self, camera: &mut Camera) {
-        if self.is_forward_pressed {
+    pub fn update_camera(&self, camera: &mut Camera, input: &Input) {
+        if input.held(Action::CameraForward) {
             camera.eye.z -= self.speed;
         }
-        if self.is_backward_pressed {
+        if input.held(Action::CameraBackward) {
             camera.eye.z += self.speed;
         }
-        if self.is_left_pressed {
+        if input.held(Action::CameraLeft) {
             camera.eye.x -= self.speed;
         }
-        if self.is_right_pressed {
+        if input.held(Action::CameraRight) {
             camera.eye.x += self.speed;
         }
     }
@@ -81,6 +35,8 @@ pub struct Camera {
     pub fov_y: f32,
     pub z_near: f32,
     pub z_far: f32,
+    /// Anything further than this from the eye is culled, even inside the frustum
+    pub draw_distance: f32,
 }
 
 impl Camera {
@@ -98,4 +54,61 @@ impl Camera {
         );
         (view, perspective_proj)
     }
+
+    pub fn frustum(&self) -> Frustum {
+        let (view, proj) = self.build_view_projection_matrix();
+        Frustum::from_matrix(proj * view)
+    }
+
+    /// True if the closest point of the box is within `draw_distance` of the eye
+    pub fn within_draw_distance(&self, min: Vec3, max: Vec3) -> bool {
+        let closest = Vec3::new(
+            self.eye.x.max(min.x).min(max.x),
+            self.eye.y.max(min.y).min(max.y),
+            self.eye.z.max(min.z).min(max.z),
+        );
+        (closest - self.eye).mag() <= self.draw_distance
+    }
+}
+
+/// The six clip planes of a view projection matrix, each as `normal.xyz` and
+/// distance `w`, with normals pointing into the frustum
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Gribb-Hartmann plane extraction for a `0..1` depth range
+    pub fn from_matrix(view_proj: Mat4) -> Self {
+        let row = |i: usize| {
+            Vec4::new(
+                view_proj.cols[0][i],
+                view_proj.cols[1][i],
+                view_proj.cols[2][i],
+                view_proj.cols[3][i],
+            )
+        };
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2];
+        for plane in planes.iter_mut() {
+            let length = Vec3::new(plane.x, plane.y, plane.z).mag();
+            *plane /= length;
+        }
+
+        Self { planes }
+    }
+
+    /// False only if the box is entirely outside one of the planes
+    pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane normal
+            let corner = Vec3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.x * corner.x + plane.y * corner.y + plane.z * corner.z + plane.w >= 0.0
+        })
+    }
 }
@@ -0,0 +1,427 @@
+//! How monsters that aren't the player's pick what to do in battle. Wild monsters act at
+//! random and trainers fight as well as the [`Difficulty`] they're given in the trainer
+//! data. AIs only look at the [`Battle`], so they can fight each other without a player.
+
+use super::battle::{Action, Battle, Outcome, Position};
+use super::rng::Rng;
+
+pub trait BattleAi {
+    /// One of [`Battle::actions`] for the monster at `position`
+    fn choose_action(&mut self, battle: &Battle, position: Position) -> Action;
+
+    /// One of the side's [`Side::bench`](super::battle::Side::bench) to take the place of
+    /// the fainted monster at `position`, only asked while the bench isn't empty
+    fn choose_replacement(&mut self, battle: &Battle, position: Position) -> usize;
+}
+
+/// Which [`BattleAi`] a trainer uses
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    /// [`RandomAi`]
+    Random,
+    /// [`GreedyAi`]
+    Greedy,
+    /// [`LookaheadAi`]
+    Lookahead,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Random" => Some(Difficulty::Random),
+            "Greedy" => Some(Difficulty::Greedy),
+            "Lookahead" => Some(Difficulty::Lookahead),
+            _ => None,
+        }
+    }
+
+    pub fn ai(self, rng: Rng) -> Box<dyn BattleAi> {
+        match self {
+            Difficulty::Random => Box::new(RandomAi::new(rng)),
+            Difficulty::Greedy => Box::new(GreedyAi::new(rng)),
+            Difficulty::Lookahead => Box::new(LookaheadAi::new(rng)),
+        }
+    }
+}
+
+/// Plays the next turn with every side's actions and replacements chosen by its AI
+pub fn play_turn(battle: &mut Battle, ais: &mut [Box<dyn BattleAi>; 2]) -> Result<(), String> {
+    // Asked again after every replacement, since sending someone out can leave the bench
+    // empty for a second slot on the same side
+    while let Some(&position) = battle.needs_replacement().first() {
+        let party_index = ais[position.side].choose_replacement(battle, position);
+        battle.replace(position, party_index)?;
+    }
+    let actions: Vec<_> = battle
+        .needs_action()
+        .into_iter()
+        .map(|position| (position, ais[position.side].choose_action(battle, position)))
+        .collect();
+    if !actions.is_empty() {
+        battle.play_turn(&actions)?;
+    }
+    Ok(())
+}
+
+/// Plays turns until the battle is over or has gone on for `max_turns`, returns how it
+/// ended or `None` if it didn't
+pub fn play_out(
+    battle: &mut Battle,
+    ais: &mut [Box<dyn BattleAi>; 2],
+    max_turns: u32,
+) -> Result<Option<Outcome>, String> {
+    while battle.outcome().is_none() && battle.turn < max_turns {
+        play_turn(battle, ais)?;
+    }
+    Ok(battle.outcome())
+}
+
+fn pick<T: Copy>(rng: &mut Rng, options: &[T]) -> T {
+    options[rng.below(options.len() as u32) as usize]
+}
+
+/// Does anything it can, how wild monsters fight
+pub struct RandomAi {
+    rng: Rng,
+}
+
+impl RandomAi {
+    pub fn new(rng: Rng) -> Self {
+        Self { rng }
+    }
+}
+
+impl BattleAi for RandomAi {
+    fn choose_action(&mut self, battle: &Battle, position: Position) -> Action {
+        pick(&mut self.rng, &battle.actions(position))
+    }
+
+    fn choose_replacement(&mut self, battle: &Battle, position: Position) -> usize {
+        pick(&mut self.rng, &battle.sides[position.side].bench())
+    }
+}
+
+/// The attack on the other side that does the most damage on average, `None` when
+/// nothing it can do would hurt them
+fn best_attack(battle: &Battle, position: Position) -> Option<(Action, f32)> {
+    let mut best: Option<(Action, f32)> = None;
+    for action in battle.actions(position) {
+        let damage = match action {
+            Action::Fight { move_slot, target } if target.side != position.side => {
+                battle.expected_damage(position, move_slot, target)
+            }
+            _ => continue,
+        };
+        if damage > best.map_or(0.0, |(_, best_damage)| best_damage) {
+            best = Some((action, damage));
+        }
+    }
+    best
+}
+
+/// Uses whichever move does the most damage this turn, going by the type chart and
+/// stats, and never switches out
+pub struct GreedyAi {
+    rng: Rng,
+}
+
+impl GreedyAi {
+    pub fn new(rng: Rng) -> Self {
+        Self { rng }
+    }
+}
+
+impl BattleAi for GreedyAi {
+    fn choose_action(&mut self, battle: &Battle, position: Position) -> Action {
+        if let Some((action, _)) = best_attack(battle, position) {
+            return action;
+        }
+
+        // Nothing does damage, so try a status move or struggle against the other side
+        let actions = battle.actions(position);
+        let at_other_side: Vec<_> = actions
+            .iter()
+            .copied()
+            .filter(|action| match action {
+                Action::Fight { target, .. } | Action::Struggle { target } => {
+                    target.side != position.side
+                }
+                Action::Switch { .. } => false,
+            })
+            .collect();
+        if at_other_side.is_empty() {
+            pick(&mut self.rng, &actions)
+        } else {
+            pick(&mut self.rng, &at_other_side)
+        }
+    }
+
+    /// Sends out whoever has the strongest attack against the other side
+    fn choose_replacement(&mut self, battle: &Battle, position: Position) -> usize {
+        let bench = battle.sides[position.side].bench();
+        let mut best = (bench[0], -1.0);
+        for &party_index in &bench {
+            let mut trial = battle.clone();
+            trial.sides[position.side].active[position.slot] = Some(party_index);
+            let damage = best_attack(&trial, position).map_or(0.0, |(_, damage)| damage);
+            if damage > best.1 {
+                best = (party_index, damage);
+            }
+        }
+        best.0
+    }
+}
+
+/// Tries every action on copies of the battle, with everyone else fighting greedily,
+/// and picks the one that leaves its side best off a few turns later. Slow next to the
+/// others, so it's meant for bosses.
+pub struct LookaheadAi {
+    rng: Rng,
+}
+
+impl LookaheadAi {
+    /// Turns played out on each copy, the first being the one the action is for
+    const TURNS: u32 = 3;
+    /// Copies played out for each action, each with different rolls
+    const SAMPLES: u32 = 4;
+    /// Added to the score for winning, and taken away for losing
+    const WIN_SCORE: f32 = 100.0;
+
+    pub fn new(rng: Rng) -> Self {
+        Self { rng }
+    }
+
+    /// The hp each side has left as a share of its whole party's, this side's minus the
+    /// other's
+    fn score(battle: &Battle, side: usize) -> f32 {
+        let health = |side: usize| -> f32 {
+            battle.sides[side]
+                .party
+                .iter()
+                .map(|monster| monster.hp as f32 / monster.stats.hp.max(1) as f32)
+                .sum()
+        };
+        let outcome = match battle.outcome() {
+            Some(Outcome::Won(winner)) if winner == side => Self::WIN_SCORE,
+            Some(Outcome::Won(_)) => -Self::WIN_SCORE,
+            _ => 0.0,
+        };
+        health(side) - health(1 - side) + outcome
+    }
+
+    /// How well `side` does on average after `first` is done to a copy of the battle and
+    /// the rest is played out greedily
+    fn evaluate(
+        &mut self,
+        battle: &Battle,
+        side: usize,
+        first: impl Fn(&mut Battle, &mut GreedyAi) -> Result<(), String>,
+    ) -> f32 {
+        let mut total = 0.0;
+        for _ in 0..Self::SAMPLES {
+            let mut trial = battle.clone();
+            trial.reseed(Rng::new(self.rng.next_u64()));
+            let mut greedy = GreedyAi::new(Rng::new(self.rng.next_u64()));
+
+            let mut result = first(&mut trial, &mut greedy);
+            for _ in 1..Self::TURNS {
+                if result.is_err() || trial.outcome().is_some() {
+                    break;
+                }
+                result = play_greedy_turn(&mut trial, &mut greedy, None);
+            }
+            total += Self::score(&trial, side);
+        }
+        total / Self::SAMPLES as f32
+    }
+}
+
+/// Plays a turn with every choice made by `greedy`, apart from `fixed` when it's given
+fn play_greedy_turn(
+    battle: &mut Battle,
+    greedy: &mut GreedyAi,
+    fixed: Option<(Position, Action)>,
+) -> Result<(), String> {
+    while let Some(&position) = battle.needs_replacement().first() {
+        let party_index = greedy.choose_replacement(battle, position);
+        battle.replace(position, party_index)?;
+    }
+    let actions: Vec<_> = battle
+        .needs_action()
+        .into_iter()
+        .map(|position| match fixed {
+            Some((fixed_position, action)) if fixed_position == position => (position, action),
+            _ => (position, greedy.choose_action(battle, position)),
+        })
+        .collect();
+    if !actions.is_empty() {
+        battle.play_turn(&actions)?;
+    }
+    Ok(())
+}
+
+impl BattleAi for LookaheadAi {
+    fn choose_action(&mut self, battle: &Battle, position: Position) -> Action {
+        let actions = battle.actions(position);
+        let mut best = (actions[0], f32::MIN);
+        for action in actions {
+            let score = self.evaluate(battle, position.side, |trial, greedy| {
+                play_greedy_turn(trial, greedy, Some((position, action)))
+            });
+            if score > best.1 {
+                best = (action, score);
+            }
+        }
+        best.0
+    }
+
+    fn choose_replacement(&mut self, battle: &Battle, position: Position) -> usize {
+        let bench = battle.sides[position.side].bench();
+        let mut best = (bench[0], f32::MIN);
+        for party_index in bench {
+            let score = self.evaluate(battle, position.side, |trial, greedy| {
+                trial.replace(position, party_index)?;
+                play_greedy_turn(trial, greedy, None)
+            });
+            if score > best.1 {
+                best = (party_index, score);
+            }
+        }
+        best.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::data::GameData;
+    use crate::game::monster::{Monster, MoveSlot};
+    use crate::game::simulation;
+
+    const DIFFICULTIES: [Difficulty; 3] = [
+        Difficulty::Random,
+        Difficulty::Greedy,
+        Difficulty::Lookahead,
+    ];
+
+    fn data() -> GameData {
+        GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap()
+    }
+
+    #[test]
+    fn every_ai_only_chooses_what_the_battle_offers() {
+        let data = data();
+        let mut rng = Rng::new(21);
+        for difficulty in DIFFICULTIES {
+            for _ in 0..4 {
+                let mut party = || -> Vec<Monster> {
+                    (0..3)
+                        .map(|_| {
+                            let species = rng.below(data.species.species.len() as u32) as usize;
+                            Monster::new(&data, species, rng.range(5, 30) as u8)
+                        })
+                        .collect()
+                };
+                let parties = [party(), party()];
+                let mut battle = Battle::new(&data, parties, 2, Rng::new(rng.next_u64())).unwrap();
+                let mut ais = [
+                    difficulty.ai(Rng::new(rng.next_u64())),
+                    difficulty.ai(Rng::new(rng.next_u64())),
+                ];
+
+                while battle.outcome().is_none() && battle.turn < 200 {
+                    while let Some(&position) = battle.needs_replacement().first() {
+                        let party_index = ais[position.side].choose_replacement(&battle, position);
+                        assert!(battle.sides[position.side].bench().contains(&party_index));
+                        battle.replace(position, party_index).unwrap();
+                    }
+                    let mut actions = Vec::new();
+                    for position in battle.needs_action() {
+                        let action = ais[position.side].choose_action(&battle, position);
+                        assert!(
+                            battle.actions(position).contains(&action),
+                            "{:?} chose {:?}",
+                            difficulty,
+                            action
+                        );
+                        actions.push((position, action));
+                    }
+                    battle.play_turn(&actions).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_picks_the_super_effective_move() {
+        let data = data();
+        let find = |name: &str| data.species.find(name).unwrap();
+        // Normal and Flying, so neither move gets the same type bonus
+        let mut attacker = Monster::new(&data, find("Hootwing"), 20);
+        attacker.moves = ["Ember", "Bubble"]
+            .iter()
+            .map(|name| MoveSlot {
+                move_index: data.moves.find(name).unwrap(),
+                pp: 10,
+            })
+            .collect();
+
+        let user = Position::new(0, 0);
+        let target = Position::new(1, 0);
+        for (defender, best_slot) in [("Emberpup", 1), ("Sproutle", 0)] {
+            let parties = [
+                vec![attacker.clone()],
+                vec![Monster::new(&data, find(defender), 20)],
+            ];
+            let battle = Battle::new(&data, parties, 1, Rng::new(1)).unwrap();
+            for seed in 0..10 {
+                assert_eq!(
+                    GreedyAi::new(Rng::new(seed)).choose_action(&battle, user),
+                    Action::Fight {
+                        move_slot: best_slot,
+                        target,
+                    },
+                    "against {}",
+                    defender
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lookahead_beats_random() {
+        let data = data();
+        let difficulties = [Difficulty::Lookahead, Difficulty::Random];
+        let mut rng = Rng::new(22);
+        let results = simulation::simulate(&data, difficulties, None, 40, &mut rng).unwrap();
+        assert!(
+            results.wins[0] > results.wins[1] * 2,
+            "won {} and lost {}",
+            results.wins[0],
+            results.wins[1]
+        );
+    }
+
+    #[test]
+    fn short_benches_in_doubles_are_replaced_one_at_a_time() {
+        let data = data();
+        let species = data.species.find("Sproutle").unwrap();
+        for difficulty in [
+            Difficulty::Random,
+            Difficulty::Greedy,
+            Difficulty::Lookahead,
+        ] {
+            let party = vec![Monster::new(&data, species, 10); 3];
+            let mut battle = Battle::new(&data, [party.clone(), party], 2, Rng::new(1)).unwrap();
+            // Both active monsters on the first side faint with one left to send out
+            battle.sides[0].party[0].hp = 0;
+            battle.sides[0].party[1].hp = 0;
+
+            let mut ais = [difficulty.ai(Rng::new(2)), difficulty.ai(Rng::new(3))];
+            play_turn(&mut battle, &mut ais).unwrap();
+            assert_eq!(battle.sides[0].active.iter().flatten().count(), 2);
+            assert!(battle.sides[0].active.contains(&Some(2)));
+            assert_eq!(battle.turn, 1);
+        }
+    }
+}
@@ -0,0 +1,1096 @@
+//! Turn-based battles between two sides, run without any rendering. What happens is
+//! decided only by the game data, the actions chosen and a seeded [`Rng`], so a battle
+//! started the same way always plays out the same. Every turn returns the [`Event`]s it
+//! caused in order, for the UI to animate.
+//!
+//! Each side has one active monster in a single battle and two in a double. A turn is
+//! played once every active monster has an [`Action`], and monsters that fainted are
+//! replaced with [`Battle::replace`] before the next one.
+
+use super::data::GameData;
+use super::monster::{Monster, Stat, Status};
+use super::moves::{Category, Effect};
+use super::rng::Rng;
+
+/// A place on the battlefield, `slot` is which of the side's active monsters stands there
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub side: usize,
+    pub slot: usize,
+}
+
+impl Position {
+    pub fn new(side: usize, slot: usize) -> Self {
+        Self { side, slot }
+    }
+}
+
+/// What an active monster does with its turn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Uses the move in `move_slot` on the monster at `target`
+    Fight { move_slot: usize, target: Position },
+    /// Flails at `target` when there's no pp left in any move, hurting itself too
+    Struggle { target: Position },
+    /// Goes back to make way for the party member at `party_index`, before any moves
+    Switch { party_index: usize },
+}
+
+/// Something that happened in a battle
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    TurnStarted {
+        turn: u32,
+    },
+    SentOut {
+        position: Position,
+        party_index: usize,
+    },
+    UsedMove {
+        user: Position,
+        /// Index into [`Moves::moves`](super::moves::Moves::moves)
+        move_index: usize,
+    },
+    Struggled {
+        user: Position,
+    },
+    /// The target fainted before the move was used and there was nobody else to aim at
+    NoTarget {
+        user: Position,
+    },
+    Missed {
+        user: Position,
+        target: Position,
+    },
+    /// A status move that had no effect
+    Failed {
+        user: Position,
+    },
+    CriticalHit {
+        target: Position,
+    },
+    /// The move's type made it do more or less damage than usual
+    Effective {
+        target: Position,
+        multiplier: f32,
+    },
+    /// The target's type takes no damage from the move's type
+    Immune {
+        target: Position,
+    },
+    Damaged {
+        target: Position,
+        amount: u16,
+        /// What's left afterwards
+        hp: u16,
+    },
+    StatusGiven {
+        target: Position,
+        status: Status,
+    },
+    StatChanged {
+        position: Position,
+        stat: Stat,
+        /// How many stages it went up, or down when negative
+        stages: i8,
+    },
+    Healed {
+        position: Position,
+        amount: u16,
+        hp: u16,
+    },
+    /// Burn or poison wearing the monster down at the end of the turn
+    StatusDamage {
+        position: Position,
+        status: Status,
+        amount: u16,
+        hp: u16,
+    },
+    FullyParalyzed {
+        position: Position,
+    },
+    Asleep {
+        position: Position,
+    },
+    WokeUp {
+        position: Position,
+    },
+    Fainted {
+        position: Position,
+    },
+    Ended {
+        outcome: Outcome,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Index of the side with monsters left standing
+    Won(usize),
+    /// The last monsters on both sides fainted in the same turn
+    Draw,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Side {
+    pub party: Vec<Monster>,
+    /// The party index of the monster in each slot, `None` once there's nobody left
+    /// to fill it
+    pub active: Vec<Option<usize>>,
+}
+
+impl Side {
+    /// Party members that could be sent out
+    pub fn bench(&self) -> Vec<usize> {
+        (0..self.party.len())
+            .filter(|index| !self.active.contains(&Some(*index)))
+            .filter(|index| !self.party[*index].is_fainted())
+            .collect()
+    }
+
+    fn is_defeated(&self) -> bool {
+        self.party.iter().all(Monster::is_fainted)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Battle<'a> {
+    data: &'a GameData,
+    pub sides: [Side; 2],
+    rng: Rng,
+    pub turn: u32,
+    /// Everything that has happened so far
+    pub log: Vec<Event>,
+}
+
+impl<'a> Battle<'a> {
+    /// Moves do half as much again when the user shares their type
+    const SAME_TYPE_BONUS: (u32, u32) = (3, 2);
+    const CRITICAL_HIT_CHANCE: u32 = 24;
+    const CRITICAL_HIT_BONUS: (u32, u32) = (3, 2);
+    /// Damage is scaled by a random percentage from this to 100
+    const MIN_DAMAGE_ROLL: u32 = 85;
+    const STRUGGLE_POWER: u32 = 50;
+    const FULLY_PARALYZED_CHANCE: f32 = 0.25;
+    const MAX_SLEEP_TURNS: u32 = 3;
+
+    /// Sends out the first `slots` healthy monsters of each party, one for a single
+    /// battle and two for a double
+    pub fn new(
+        data: &'a GameData,
+        parties: [Vec<Monster>; 2],
+        slots: usize,
+        rng: Rng,
+    ) -> Result<Self, String> {
+        if slots == 0 || slots > 2 {
+            return Err(format!("battles have 1 or 2 slots a side, not {}", slots));
+        }
+
+        let [first, second] = parties;
+        let mut sides = [
+            Side {
+                party: first,
+                active: vec![None; slots],
+            },
+            Side {
+                party: second,
+                active: vec![None; slots],
+            },
+        ];
+        let mut log = Vec::new();
+        for (side_index, side) in sides.iter_mut().enumerate() {
+            if side.is_defeated() {
+                return Err(format!(
+                    "side {} has no monsters that can battle",
+                    side_index
+                ));
+            }
+            for slot in 0..slots {
+                if let Some(&party_index) = side.bench().first() {
+                    side.active[slot] = Some(party_index);
+                    log.push(Event::SentOut {
+                        position: Position::new(side_index, slot),
+                        party_index,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            data,
+            sides,
+            rng,
+            turn: 0,
+            log,
+        })
+    }
+
+    /// Replaces the random number generator, so AIs can try out turns on a copy of the
+    /// battle without knowing how the real rolls will go
+    pub fn reseed(&mut self, rng: Rng) {
+        self.rng = rng;
+    }
+
+    /// The monster at `position`, fainted or not
+    pub fn monster(&self, position: Position) -> Option<&Monster> {
+        let side = self.sides.get(position.side)?;
+        let party_index = (*side.active.get(position.slot)?)?;
+        side.party.get(party_index)
+    }
+
+    fn monster_mut(&mut self, position: Position) -> Option<&mut Monster> {
+        let side = self.sides.get_mut(position.side)?;
+        let party_index = (*side.active.get(position.slot)?)?;
+        side.party.get_mut(party_index)
+    }
+
+    fn is_standing(&self, position: Position) -> bool {
+        self.monster(position)
+            .map_or(false, |monster| !monster.is_fainted())
+    }
+
+    /// Every position with a monster that hasn't fainted, side by side and slot by slot
+    pub fn standing(&self) -> Vec<Position> {
+        let mut positions = Vec::new();
+        for (side, side_data) in self.sides.iter().enumerate() {
+            for slot in 0..side_data.active.len() {
+                let position = Position::new(side, slot);
+                if self.is_standing(position) {
+                    positions.push(position);
+                }
+            }
+        }
+        positions
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        match (self.sides[0].is_defeated(), self.sides[1].is_defeated()) {
+            (true, true) => Some(Outcome::Draw),
+            (true, false) => Some(Outcome::Won(1)),
+            (false, true) => Some(Outcome::Won(0)),
+            (false, false) => None,
+        }
+    }
+
+    /// Slots whose monster fainted and that have someone on the bench to take over
+    pub fn needs_replacement(&self) -> Vec<Position> {
+        if self.outcome().is_some() {
+            return Vec::new();
+        }
+        let mut positions = Vec::new();
+        for (side, side_data) in self.sides.iter().enumerate() {
+            if side_data.bench().is_empty() {
+                continue;
+            }
+            for slot in 0..side_data.active.len() {
+                let position = Position::new(side, slot);
+                if !self.is_standing(position) {
+                    positions.push(position);
+                }
+            }
+        }
+        positions
+    }
+
+    /// Sends out the party member at `party_index` in place of the fainted monster at
+    /// `position`
+    pub fn replace(&mut self, position: Position, party_index: usize) -> Result<&[Event], String> {
+        if !self.needs_replacement().contains(&position) {
+            return Err(format!("{:?} doesn't need replacing", position));
+        }
+        if !self.sides[position.side].bench().contains(&party_index) {
+            return Err(format!(
+                "party member {} of side {} can't be sent out",
+                party_index, position.side
+            ));
+        }
+
+        let start = self.log.len();
+        self.send_out(position, party_index);
+        Ok(&self.log[start..])
+    }
+
+    fn send_out(&mut self, position: Position, party_index: usize) {
+        if let Some(monster) = self.monster_mut(position) {
+            monster.reset_stages();
+        }
+        self.sides[position.side].active[position.slot] = Some(party_index);
+        if let Some(monster) = self.monster_mut(position) {
+            monster.reset_stages();
+        }
+        self.log.push(Event::SentOut {
+            position,
+            party_index,
+        });
+    }
+
+    /// The positions that need an action before the turn can be played, none while a
+    /// fainted monster is waiting to be replaced or once the battle is over
+    pub fn needs_action(&self) -> Vec<Position> {
+        if self.outcome().is_some() || !self.needs_replacement().is_empty() {
+            return Vec::new();
+        }
+        self.standing()
+    }
+
+    /// Everything the monster at `position` can do this turn
+    pub fn actions(&self, position: Position) -> Vec<Action> {
+        let monster = match self.monster(position) {
+            Some(monster) if !monster.is_fainted() => monster,
+            _ => return Vec::new(),
+        };
+        let targets: Vec<_> = self
+            .standing()
+            .into_iter()
+            .filter(|target| *target != position)
+            .collect();
+
+        let mut actions = Vec::new();
+        for (move_slot, slot) in monster.moves.iter().enumerate() {
+            if slot.pp > 0 {
+                actions.extend(
+                    targets
+                        .iter()
+                        .map(|&target| Action::Fight { move_slot, target }),
+                );
+            }
+        }
+        if actions.is_empty() {
+            actions.extend(targets.iter().map(|&target| Action::Struggle { target }));
+        }
+        actions.extend(
+            self.sides[position.side]
+                .bench()
+                .into_iter()
+                .map(|party_index| Action::Switch { party_index }),
+        );
+        actions
+    }
+
+    /// Plays a turn with an action for every position in [`Battle::needs_action`].
+    /// Switches happen first, then moves from the highest priority to the lowest and the
+    /// fastest monster to the slowest, and burn and poison hurt at the end.
+    pub fn play_turn(&mut self, actions: &[(Position, Action)]) -> Result<&[Event], String> {
+        let needed = self.needs_action();
+        if needed.is_empty() {
+            return Err("no actions are needed".to_string());
+        }
+        for position in &needed {
+            let chosen: Vec<_> = actions
+                .iter()
+                .filter(|(actor, _)| actor == position)
+                .collect();
+            match chosen.as_slice() {
+                [(_, action)] if self.actions(*position).contains(action) => {}
+                [(_, action)] => {
+                    return Err(format!("{:?} can't be done by {:?}", action, position))
+                }
+                [] => return Err(format!("{:?} has no action", position)),
+                _ => return Err(format!("{:?} has more than one action", position)),
+            }
+        }
+        if let Some((position, _)) = actions.iter().find(|(actor, _)| !needed.contains(actor)) {
+            return Err(format!("{:?} can't act this turn", position));
+        }
+
+        let start = self.log.len();
+        self.turn += 1;
+        self.log.push(Event::TurnStarted { turn: self.turn });
+
+        // Ties are broken with a roll for each position in a fixed order, so the order
+        // actions were given in can't change the result
+        let mut ordered: Vec<_> = actions.to_vec();
+        ordered.sort_by_key(|(position, _)| *position);
+        let mut ordered: Vec<_> = ordered
+            .into_iter()
+            .map(|(position, action)| {
+                let party_index = self.sides[position.side].active[position.slot];
+                (
+                    self.turn_order(position, action),
+                    position,
+                    action,
+                    party_index,
+                )
+            })
+            .collect();
+        ordered.sort_by_key(|&(order, ..)| std::cmp::Reverse(order));
+
+        for (_, position, action, party_index) in ordered {
+            // Monsters that fainted or were switched out earlier in the turn lose their turn
+            if self.sides[position.side].active[position.slot] != party_index
+                || !self.is_standing(position)
+            {
+                continue;
+            }
+            match action {
+                // Both monsters of a double can pick the same one to switch in, the
+                // second one stays in having lost its turn
+                Action::Switch { party_index }
+                    if self.sides[position.side]
+                        .active
+                        .contains(&Some(party_index)) => {}
+                Action::Switch { party_index } => self.send_out(position, party_index),
+                Action::Fight { move_slot, target } => self.use_move(position, move_slot, target),
+                Action::Struggle { target } => self.struggle(position, target),
+            }
+            if self.outcome().is_some() {
+                break;
+            }
+        }
+
+        if self.outcome().is_none() {
+            self.end_of_turn();
+        }
+        if let Some(outcome) = self.outcome() {
+            self.log.push(Event::Ended { outcome });
+        }
+        Ok(&self.log[start..])
+    }
+
+    /// Bigger goes first, switches before anything else
+    fn turn_order(&mut self, position: Position, action: Action) -> (bool, i8, u16, u64) {
+        let tiebreak = self.rng.next_u64();
+        let is_switch = matches!(action, Action::Switch { .. });
+        let priority = match (action, self.monster(position)) {
+            (Action::Fight { move_slot, .. }, Some(monster)) => {
+                self.data.moves.moves[monster.moves[move_slot].move_index].priority
+            }
+            _ => 0,
+        };
+        (is_switch, priority, self.speed(position), tiebreak)
+    }
+
+    fn speed(&self, position: Position) -> u16 {
+        match self.monster(position) {
+            Some(monster) if monster.status == Some(Status::Paralysis) => {
+                monster.stat(Stat::Speed) / 2
+            }
+            Some(monster) => monster.stat(Stat::Speed),
+            None => 0,
+        }
+    }
+
+    /// Whether the monster at `position` shakes off sleep or paralysis to act this turn
+    fn can_act(&mut self, position: Position) -> bool {
+        let (status, sleep_turns) = match self.monster(position) {
+            Some(monster) => (monster.status, monster.sleep_turns),
+            None => return false,
+        };
+        match status {
+            Some(Status::Sleep) if sleep_turns > 0 => {
+                if let Some(monster) = self.monster_mut(position) {
+                    monster.sleep_turns -= 1;
+                }
+                self.log.push(Event::Asleep { position });
+                false
+            }
+            Some(Status::Sleep) => {
+                if let Some(monster) = self.monster_mut(position) {
+                    monster.status = None;
+                }
+                self.log.push(Event::WokeUp { position });
+                true
+            }
+            Some(Status::Paralysis) if self.rng.chance(Self::FULLY_PARALYZED_CHANCE) => {
+                self.log.push(Event::FullyParalyzed { position });
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// `target` if its monster is still standing, otherwise another monster on the same
+    /// side or failing that any other
+    fn retarget(&self, user: Position, target: Position) -> Option<Position> {
+        if target != user && self.is_standing(target) {
+            return Some(target);
+        }
+        let others: Vec<_> = self
+            .standing()
+            .into_iter()
+            .filter(|other| *other != user)
+            .collect();
+        others
+            .iter()
+            .find(|other| other.side == target.side)
+            .or_else(|| others.first())
+            .copied()
+    }
+
+    fn use_move(&mut self, user: Position, move_slot: usize, target: Position) {
+        if !self.can_act(user) {
+            return;
+        }
+        let data = self.data;
+        let move_index = match self.monster_mut(user) {
+            Some(monster) => {
+                let slot = &mut monster.moves[move_slot];
+                slot.pp = slot.pp.saturating_sub(1);
+                slot.move_index
+            }
+            None => return,
+        };
+        let move_data = &data.moves.moves[move_index];
+        self.log.push(Event::UsedMove { user, move_index });
+
+        let target = match self.retarget(user, target) {
+            Some(target) => target,
+            None => {
+                self.log.push(Event::NoTarget { user });
+                return;
+            }
+        };
+        if let Some(accuracy) = move_data.accuracy {
+            if self.rng.below(100) >= accuracy as u32 {
+                self.log.push(Event::Missed { user, target });
+                return;
+            }
+        }
+
+        let mut dealt = 0;
+        if move_data.category != Category::Status {
+            let effectiveness = self
+                .monster(target)
+                .map_or(1.0, |monster| data.effectiveness(move_index, monster));
+            if effectiveness == 0.0 {
+                self.log.push(Event::Immune { target });
+                return;
+            }
+            let same_type = self.monster(user).map_or(false, |monster| {
+                monster.species(data).types.contains(&move_data.move_type)
+            });
+            let amount = self.damage(
+                user,
+                target,
+                move_data.category,
+                move_data.power as u32,
+                same_type,
+                effectiveness,
+            );
+            dealt = self.hurt(target, amount);
+        }
+
+        let mut had_effect = false;
+        for secondary in &move_data.effects {
+            if secondary.chance < 100 && self.rng.below(100) >= secondary.chance as u32 {
+                continue;
+            }
+            // Recoil and drain are a share of the damage done, but always at least 1
+            let share = |percent: u8| {
+                if dealt == 0 {
+                    0
+                } else {
+                    (dealt as u32 * percent as u32 / 100).max(1) as u16
+                }
+            };
+            had_effect |= match secondary.effect {
+                Effect::Status(status) => self.give_status(target, status),
+                Effect::Stages {
+                    stat,
+                    stages,
+                    on_user,
+                } => self.change_stage(if on_user { user } else { target }, stat, stages),
+                Effect::Recoil(percent) => {
+                    share(percent) > 0 && self.hurt(user, share(percent)) > 0
+                }
+                Effect::Drain(percent) => self.heal(user, share(percent)),
+            };
+        }
+        if move_data.category == Category::Status && !had_effect {
+            self.log.push(Event::Failed { user });
+        }
+    }
+
+    fn struggle(&mut self, user: Position, target: Position) {
+        if !self.can_act(user) {
+            return;
+        }
+        self.log.push(Event::Struggled { user });
+
+        let target = match self.retarget(user, target) {
+            Some(target) => target,
+            None => {
+                self.log.push(Event::NoTarget { user });
+                return;
+            }
+        };
+        // Struggling has no type, so it hits everything normally
+        let amount = self.damage(
+            user,
+            target,
+            Category::Physical,
+            Self::STRUGGLE_POWER,
+            false,
+            1.0,
+        );
+        self.hurt(target, amount);
+
+        let recoil = self
+            .monster(user)
+            .map_or(0, |monster| (monster.stats.hp / 4).max(1));
+        self.hurt(user, recoil);
+    }
+
+    /// Rolls how much damage a move of `power` from `user` does to `target`, adding
+    /// events for critical hits and type matchups. `effectiveness` is the multiplier for
+    /// the move's type against the target's, see [`GameData::effectiveness`].
+    fn damage(
+        &mut self,
+        user: Position,
+        target: Position,
+        category: Category,
+        power: u32,
+        same_type: bool,
+        effectiveness: f32,
+    ) -> u16 {
+        let mut damage = match (self.monster(user), self.monster(target)) {
+            (Some(attacker), Some(defender)) => Self::base_damage(
+                attacker,
+                defender,
+                category,
+                power,
+                same_type,
+                effectiveness,
+            ),
+            _ => return 0,
+        };
+        if self.rng.below(Self::CRITICAL_HIT_CHANCE) == 0 {
+            damage = damage * Self::CRITICAL_HIT_BONUS.0 / Self::CRITICAL_HIT_BONUS.1;
+            self.log.push(Event::CriticalHit { target });
+        }
+        if effectiveness != 1.0 {
+            self.log.push(Event::Effective {
+                target,
+                multiplier: effectiveness,
+            });
+        }
+        damage = damage * self.rng.range(Self::MIN_DAMAGE_ROLL, 100) / 100;
+
+        damage.max(1).min(u16::MAX as u32) as u16
+    }
+
+    /// Damage before critical hits and the random roll
+    fn base_damage(
+        attacker: &Monster,
+        defender: &Monster,
+        category: Category,
+        power: u32,
+        same_type: bool,
+        effectiveness: f32,
+    ) -> u32 {
+        let (attack, defense) = match category {
+            Category::Special => (
+                attacker.stat(Stat::SpecialAttack),
+                defender.stat(Stat::SpecialDefense),
+            ),
+            _ => (attacker.stat(Stat::Attack), defender.stat(Stat::Defense)),
+        };
+        let burned = category == Category::Physical && attacker.status == Some(Status::Burn);
+
+        let level = attacker.level as u32;
+        let mut damage =
+            (2 * level / 5 + 2) * power * attack as u32 / (defense as u32).max(1) / 50 + 2;
+        if same_type {
+            damage = damage * Self::SAME_TYPE_BONUS.0 / Self::SAME_TYPE_BONUS.1;
+        }
+        damage = (damage as f32 * effectiveness) as u32;
+        if burned {
+            damage /= 2;
+        }
+        damage
+    }
+
+    /// The damage the move in `move_slot` of the monster at `user` does to the one at
+    /// `target` on average, counting misses, critical hits and the random roll. 0 for
+    /// status moves or when either monster isn't standing.
+    pub fn expected_damage(&self, user: Position, move_slot: usize, target: Position) -> f32 {
+        let (attacker, defender) = match (self.monster(user), self.monster(target)) {
+            (Some(attacker), Some(defender))
+                if !attacker.is_fainted() && !defender.is_fainted() =>
+            {
+                (attacker, defender)
+            }
+            _ => return 0.0,
+        };
+        let move_index = match attacker.moves.get(move_slot) {
+            Some(slot) => slot.move_index,
+            None => return 0.0,
+        };
+        let move_data = &self.data.moves.moves[move_index];
+        if move_data.category == Category::Status {
+            return 0.0;
+        }
+
+        let same_type = attacker
+            .species(self.data)
+            .types
+            .contains(&move_data.move_type);
+        let effectiveness = self.data.effectiveness(move_index, defender);
+        let damage = Self::base_damage(
+            attacker,
+            defender,
+            move_data.category,
+            move_data.power as u32,
+            same_type,
+            effectiveness,
+        ) as f32;
+
+        let (bonus, over) = Self::CRITICAL_HIT_BONUS;
+        let critical = 1.0 + (bonus as f32 / over as f32 - 1.0) / Self::CRITICAL_HIT_CHANCE as f32;
+        let roll = (Self::MIN_DAMAGE_ROLL + 100) as f32 / 200.0;
+        let accuracy = move_data
+            .accuracy
+            .map_or(1.0, |accuracy| accuracy as f32 / 100.0);
+        // Damage past the target's hp is wasted
+        let hp = defender.hp as f32;
+        (damage * critical * roll).min(hp) * accuracy
+    }
+
+    /// Returns how much hp `target` lost
+    fn hurt(&mut self, target: Position, amount: u16) -> u16 {
+        let monster = match self.monster_mut(target) {
+            Some(monster) => monster,
+            None => return 0,
+        };
+        let amount = monster.damage(amount);
+        let hp = monster.hp;
+        self.log.push(Event::Damaged { target, amount, hp });
+        if hp == 0 {
+            self.log.push(Event::Fainted { position: target });
+        }
+        amount
+    }
+
+    /// Returns whether `position` got any hp back, fainted monsters can't
+    fn heal(&mut self, position: Position, amount: u16) -> bool {
+        let monster = match self.monster_mut(position) {
+            Some(monster) if !monster.is_fainted() => monster,
+            _ => return false,
+        };
+        let amount = amount.min(monster.stats.hp - monster.hp);
+        if amount == 0 {
+            return false;
+        }
+        monster.hp += amount;
+        let hp = monster.hp;
+        self.log.push(Event::Healed {
+            position,
+            amount,
+            hp,
+        });
+        true
+    }
+
+    /// Returns whether the stat moved, it can't past [`Monster::MAX_STAGE`] either way
+    fn change_stage(&mut self, position: Position, stat: Stat, stages: i8) -> bool {
+        let monster = match self.monster_mut(position) {
+            Some(monster) if !monster.is_fainted() => monster,
+            _ => return false,
+        };
+        let stages = monster.change_stage(stat, stages);
+        if stages == 0 {
+            return false;
+        }
+        self.log.push(Event::StatChanged {
+            position,
+            stat,
+            stages,
+        });
+        true
+    }
+
+    /// Returns whether `target` got the status, it can't while it already has one
+    fn give_status(&mut self, target: Position, status: Status) -> bool {
+        let sleep_turns = match status {
+            Status::Sleep => self.rng.range(1, Self::MAX_SLEEP_TURNS) as u8,
+            _ => 0,
+        };
+        let monster = match self.monster_mut(target) {
+            Some(monster) if !monster.is_fainted() && monster.status.is_none() => monster,
+            _ => return false,
+        };
+        monster.status = Some(status);
+        monster.sleep_turns = sleep_turns;
+        self.log.push(Event::StatusGiven { target, status });
+        true
+    }
+
+    fn end_of_turn(&mut self) {
+        for position in self.standing() {
+            let monster = match self.monster_mut(position) {
+                Some(monster) => monster,
+                None => continue,
+            };
+            let (status, fraction) = match monster.status {
+                Some(Status::Burn) => (Status::Burn, 16),
+                Some(Status::Poison) => (Status::Poison, 8),
+                _ => continue,
+            };
+            let amount = monster.damage((monster.stats.hp / fraction).max(1));
+            let hp = monster.hp;
+            self.log.push(Event::StatusDamage {
+                position,
+                status,
+                amount,
+                hp,
+            });
+            if hp == 0 {
+                self.log.push(Event::Fainted { position });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::ai::{self, Difficulty};
+    use crate::game::monster::MoveSlot;
+
+    fn data() -> GameData {
+        GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap()
+    }
+
+    /// A level 20 `species` that only knows `move_name`, with `hp` and `speed` set so
+    /// tests can tell who goes first and who faints
+    fn monster(data: &GameData, species: &str, move_name: &str, hp: u16, speed: u16) -> Monster {
+        let mut monster = Monster::new(data, data.species.find(species).unwrap(), 20);
+        monster.moves = vec![MoveSlot {
+            move_index: data.moves.find(move_name).unwrap(),
+            pp: 40,
+        }];
+        monster.stats.hp = hp;
+        monster.hp = hp;
+        monster.stats.speed = speed;
+        monster
+    }
+
+    /// Both sides of a single battle use their only move on each other
+    fn trade_moves(battle: &mut Battle) -> Result<(), String> {
+        let (first, second) = (Position::new(0, 0), Position::new(1, 0));
+        battle.play_turn(&[
+            (
+                first,
+                Action::Fight {
+                    move_slot: 0,
+                    target: second,
+                },
+            ),
+            (
+                second,
+                Action::Fight {
+                    move_slot: 0,
+                    target: first,
+                },
+            ),
+        ])?;
+        Ok(())
+    }
+
+    fn users(log: &[Event]) -> Vec<Position> {
+        log.iter()
+            .filter_map(|event| match event {
+                Event::UsedMove { user, .. } => Some(*user),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_plays_out_the_same() {
+        let data = data();
+        let play = |seed: u64| {
+            let party = |species: &str| {
+                let species = data.species.find(species).unwrap();
+                vec![
+                    Monster::new(&data, species, 12),
+                    Monster::new(&data, species, 14),
+                ]
+            };
+            let parties = [party("Sproutle"), party("Emberpup")];
+            let mut battle = Battle::new(&data, parties, 2, Rng::new(seed)).unwrap();
+            let mut ais = [
+                Difficulty::Random.ai(Rng::new(seed + 1)),
+                Difficulty::Random.ai(Rng::new(seed + 2)),
+            ];
+            ai::play_out(&mut battle, &mut ais, 1000).unwrap();
+            battle.log
+        };
+
+        let log = play(5);
+        assert!(matches!(log.last(), Some(Event::Ended { .. })));
+        assert_eq!(play(5), log);
+    }
+
+    #[test]
+    fn faster_monsters_go_first_unless_outranked() {
+        let data = data();
+        let slow = monster(&data, "Sproutle", "Tackle", 500, 10);
+        let fast = monster(&data, "Emberpup", "Tackle", 500, 100);
+
+        let parties = [vec![slow.clone()], vec![fast.clone()]];
+        let mut battle = Battle::new(&data, parties, 1, Rng::new(1)).unwrap();
+        trade_moves(&mut battle).unwrap();
+        assert_eq!(
+            users(&battle.log),
+            [Position::new(1, 0), Position::new(0, 0)]
+        );
+
+        // Quick Attack has priority, so speed no longer matters
+        let quick = monster(&data, "Sproutle", "Quick Attack", 500, 10);
+        let mut battle = Battle::new(&data, [vec![quick], vec![fast]], 1, Rng::new(1)).unwrap();
+        trade_moves(&mut battle).unwrap();
+        assert_eq!(
+            users(&battle.log),
+            [Position::new(0, 0), Position::new(1, 0)]
+        );
+    }
+
+    #[test]
+    fn moves_that_miss_do_no_damage() {
+        let mut data = data();
+        let tackle = data.moves.find("Tackle").unwrap();
+        data.moves.moves[tackle].accuracy = Some(0);
+
+        let parties = [
+            vec![monster(&data, "Sproutle", "Tackle", 100, 10)],
+            vec![monster(&data, "Emberpup", "Tackle", 100, 20)],
+        ];
+        let mut battle = Battle::new(&data, parties, 1, Rng::new(2)).unwrap();
+        for _ in 0..10 {
+            trade_moves(&mut battle).unwrap();
+        }
+
+        let misses = battle
+            .log
+            .iter()
+            .filter(|event| matches!(event, Event::Missed { .. }))
+            .count();
+        assert_eq!(misses, 20);
+        assert!(!battle
+            .log
+            .iter()
+            .any(|event| matches!(event, Event::Damaged { .. })));
+    }
+
+    #[test]
+    fn burn_and_poison_hurt_at_the_end_of_the_turn() {
+        let data = data();
+        let mut poisoned = monster(&data, "Sproutle", "Howl", 160, 10);
+        poisoned.status = Some(Status::Poison);
+        let mut burned = monster(&data, "Emberpup", "Howl", 160, 20);
+        burned.status = Some(Status::Burn);
+
+        let parties = [vec![poisoned], vec![burned]];
+        let mut battle = Battle::new(&data, parties, 1, Rng::new(3)).unwrap();
+        trade_moves(&mut battle).unwrap();
+
+        let last_move = battle
+            .log
+            .iter()
+            .rposition(|event| matches!(event, Event::UsedMove { .. }))
+            .unwrap();
+        assert_eq!(
+            battle.log[last_move + 1..]
+                .iter()
+                .filter(|event| matches!(event, Event::StatusDamage { .. }))
+                .collect::<Vec<_>>(),
+            [
+                &Event::StatusDamage {
+                    position: Position::new(0, 0),
+                    status: Status::Poison,
+                    amount: 20,
+                    hp: 140,
+                },
+                &Event::StatusDamage {
+                    position: Position::new(1, 0),
+                    status: Status::Burn,
+                    amount: 10,
+                    hp: 150,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_monster_switched_in_twice_only_comes_out_once() {
+        let data = data();
+        let party = vec![monster(&data, "Sproutle", "Howl", 100, 10); 3];
+        let mut battle = Battle::new(&data, [party.clone(), party], 2, Rng::new(6)).unwrap();
+
+        let mut actions = Vec::new();
+        for slot in 0..2 {
+            actions.push((Position::new(0, slot), Action::Switch { party_index: 2 }));
+            actions.push((
+                Position::new(1, slot),
+                Action::Fight {
+                    move_slot: 0,
+                    target: Position::new(0, 0),
+                },
+            ));
+        }
+        let events = battle.play_turn(&actions).unwrap().to_vec();
+
+        // Whichever slot rolled to go first is the one that switches
+        let sent_out: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::SentOut {
+                    position,
+                    party_index,
+                } => Some((*position, *party_index)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sent_out.len(), 1);
+        let (position, party_index) = sent_out[0];
+        assert_eq!(party_index, 2);
+        let mut active = vec![Some(0), Some(1)];
+        active[position.slot] = Some(2);
+        assert_eq!(battle.sides[0].active, active);
+    }
+
+    #[test]
+    fn fainted_monsters_are_replaced_until_a_side_wins() {
+        let data = data();
+        let parties = [
+            vec![
+                monster(&data, "Sproutle", "Tackle", 1, 10),
+                monster(&data, "Puddlet", "Tackle", 500, 10),
+            ],
+            vec![monster(&data, "Emberpup", "Tackle", 1, 100)],
+        ];
+        let mut battle = Battle::new(&data, parties, 1, Rng::new(4)).unwrap();
+
+        // The faster side knocks out the first monster before it can act
+        trade_moves(&mut battle).unwrap();
+        let fainted = Position::new(0, 0);
+        assert!(battle.log.contains(&Event::Fainted { position: fainted }));
+        assert_eq!(users(&battle.log), [Position::new(1, 0)]);
+        assert_eq!(battle.outcome(), None);
+        assert_eq!(battle.needs_replacement(), [fainted]);
+        assert!(battle.needs_action().is_empty());
+        assert!(trade_moves(&mut battle).is_err());
+
+        assert!(battle.replace(fainted, 0).is_err());
+        let events = battle.replace(fainted, 1).unwrap().to_vec();
+        assert_eq!(
+            events,
+            [Event::SentOut {
+                position: fainted,
+                party_index: 1,
+            }]
+        );
+
+        trade_moves(&mut battle).unwrap();
+        assert_eq!(battle.outcome(), Some(Outcome::Won(0)));
+        assert_eq!(
+            battle.log.last(),
+            Some(&Event::Ended {
+                outcome: Outcome::Won(0)
+            })
+        );
+        assert!(battle.needs_replacement().is_empty());
+        assert!(trade_moves(&mut battle).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Game content written by designers as RON files in [`DIR`] and loaded once at startup.
+//! Each file is checked as a whole and every mistake in it is reported, not just the
+//! first, as `path:line: message` so they can all be fixed in one go.
+
+use super::monster::Monster;
+use super::moves::{Category, Moves};
+use super::species::SpeciesRegistry;
+use super::trainer::Trainers;
+use super::types::Types;
+use crate::renderer::assets::AssetManager;
+use nanoserde::DeRon;
+use std::path::Path;
+
+/// Next to the map, like the encounter file and the assets directory
+pub const DIR: &str = "data";
+
+/// The text of a data file, kept around to point errors at the line they're about
+pub struct Source {
+    pub path: String,
+    pub text: String,
+}
+
+impl Source {
+    pub fn read(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self {
+            path: path.to_string(),
+            text,
+        })
+    }
+
+    fn parse<T: DeRon>(&self) -> Result<T, String> {
+        T::deserialize_ron(&self.text)
+            .map_err(|e| format!("{}:{}:{}: {:?}", self.path, e.line + 1, e.col + 1, e.msg))
+    }
+
+    /// Reads and parses the file at `path`, adding to `errors` when either fails
+    pub fn load<T: DeRon>(path: &str, errors: &mut Vec<String>) -> Option<(Self, T)> {
+        match Self::read(path) {
+            Ok(source) => source.parsed(errors),
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        }
+    }
+
+    /// Parses the text, adding to `errors` when it fails
+    pub fn parsed<T: DeRon>(self, errors: &mut Vec<String>) -> Option<(Self, T)> {
+        match self.parse() {
+            Ok(config) => Some((self, config)),
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        }
+    }
+
+    /// An error on the first line that mentions `needle`, such as the quoted name of
+    /// whatever is wrong. Comments are skipped and the first line is used when nothing
+    /// mentions it.
+    pub fn error(&self, needle: &str, message: impl std::fmt::Display) -> String {
+        format!("{}:{}: {}", self.path, self.line_of(needle, 0), message)
+    }
+
+    /// An error on the second line that mentions `needle`, or the first if there's only
+    /// one, for something defined twice
+    pub fn duplicate_error(&self, needle: &str, message: impl std::fmt::Display) -> String {
+        format!("{}:{}: {}", self.path, self.line_of(needle, 1), message)
+    }
+
+    /// An error on the first line that mentions `needle` from the first that mentions
+    /// `after` on, for something inside a block such as an entry of a named table
+    pub fn error_after(
+        &self,
+        after: &str,
+        needle: &str,
+        message: impl std::fmt::Display,
+    ) -> String {
+        let start = self.line_of(after, 0);
+        let line = self.line_from(start, needle, 0).unwrap_or(start);
+        format!("{}:{}: {}", self.path, line, message)
+    }
+
+    fn line_of(&self, needle: &str, skip: usize) -> usize {
+        self.line_from(1, needle, skip).unwrap_or(1)
+    }
+
+    fn line_from(&self, start: usize, needle: &str, skip: usize) -> Option<usize> {
+        self.text
+            .lines()
+            .enumerate()
+            .skip(start - 1)
+            .filter(|(_, line)| {
+                let code = line.split("//").next().unwrap_or("");
+                code.contains(needle)
+            })
+            .take(skip + 1)
+            .last()
+            .map(|(n, _)| n + 1)
+    }
+}
+
+/// `name` in quotes, the way it's written in a data file
+pub fn quoted(name: &str) -> String {
+    format!("\"{}\"", name)
+}
+
+/// The types, moves, species and trainers battles are built from. Later files refer to
+/// names defined in earlier ones, so they're loaded in that order.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct GameData {
+    pub types: Types,
+    pub moves: Moves,
+    pub species: SpeciesRegistry,
+    pub trainers: Trainers,
+}
+
+impl GameData {
+    /// Loads `types.ron`, `moves.ron`, every file in `species` and `trainers.ron` from
+    /// `dir`, with the assets directory beside it for species sprites. Whatever is wrong in one file doesn't stop the others being checked, and all
+    /// the errors found are returned together, one per line.
+    pub fn load(dir: &str) -> Result<Self, String> {
+        let mut errors = Vec::new();
+        let types = Types::load(&format!("{}/{}", dir, Types::FILE), &mut errors);
+        let moves = Moves::load(&format!("{}/{}", dir, Moves::FILE), &types, &mut errors);
+        let assets = Path::new(dir)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(AssetManager::ROOT);
+        let species = SpeciesRegistry::load(
+            &format!("{}/{}", dir, SpeciesRegistry::DIR),
+            &assets,
+            &types,
+            &moves,
+            &mut errors,
+        );
+        let trainers = Trainers::load(
+            &format!("{}/{}", dir, Trainers::FILE),
+            &species,
+            &mut errors,
+        );
+
+        if errors.is_empty() {
+            Ok(Self {
+                types,
+                moves,
+                species,
+                trainers,
+            })
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// How much damage the move at `move_index` does to `monster` because of their types,
+    /// 0 when the monster is immune. Status moves aren't affected by types and are 1.
+    pub fn effectiveness(&self, move_index: usize, monster: &Monster) -> f32 {
+        let move_data = &self.moves.moves[move_index];
+        if move_data.category == Category::Status {
+            return 1.0;
+        }
+        self.types
+            .effectiveness_against(move_data.move_type, &monster.species(self).types)
+    }
+}
@@ -0,0 +1,236 @@
+use super::map::{Map, Tile};
+use crate::input::{Action, Input};
+use std::path::PathBuf;
+
+/// Everything the editor can do to the map, so it can be driven without key bindings
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditorAction {
+    MoveCursor(isize, isize),
+    RaiseTile,
+    LowerTile,
+    SwapModel,
+    SwapMaterial,
+    SwapPassability,
+    /// Paints the selected tile with the next encounter zone
+    SwapZone,
+    RotateTile,
+    /// Makes the selected tile where play mode starts
+    SetSpawn,
+    /// Shows or hides the passability overlay
+    ShowPassability,
+    SaveMap,
+}
+
+/// Cursor and map editing, kept apart from the renderer so it can be driven headlessly
+pub struct Editor {
+    pub selected: (isize, isize),
+    pub map: Map,
+    /// Whether the passability overlay is drawn
+    pub show_passability: bool,
+
+    /// Where [`EditorAction::SaveMap`] writes the map
+    pub path: PathBuf,
+}
+
+impl Editor {
+    pub fn new(map: Map, path: impl Into<PathBuf>) -> Self {
+        Self {
+            selected: (0, 0),
+            map,
+            show_passability: false,
+            path: path.into(),
+        }
+    }
+
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let map = Map::load(&path)?;
+        Ok(Self::new(map, path))
+    }
+
+    pub fn selected_tile(&mut self) -> Option<&mut Tile> {
+        self.map.get_tile_mut(self.selected.0, self.selected.1)
+    }
+
+    pub fn apply(&mut self, action: EditorAction) -> Result<(), String> {
+        match action {
+            EditorAction::MoveCursor(x, y) => {
+                self.selected.0 += x;
+                self.selected.1 += y;
+            }
+            EditorAction::RaiseTile => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.height = tile.height.saturating_add(1);
+                }
+            }
+            EditorAction::LowerTile => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.height = tile.height.saturating_sub(1);
+                }
+            }
+            EditorAction::SwapModel => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.model.swap_model();
+                }
+            }
+            EditorAction::SwapMaterial => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.material.swap_material();
+                }
+            }
+            EditorAction::SwapPassability => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.passability.swap_passability();
+                }
+            }
+            EditorAction::SwapZone => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.swap_zone();
+                }
+            }
+            EditorAction::RotateTile => {
+                if let Some(tile) = self.selected_tile() {
+                    tile.rotation.rotate_next();
+                }
+            }
+            EditorAction::SetSpawn => self.map.spawn = self.selected,
+            EditorAction::ShowPassability => self.show_passability = !self.show_passability,
+            EditorAction::SaveMap => self.map.save(&self.path)?,
+        }
+
+        Ok(())
+    }
+
+    /// The editor actions bound to this frame's input, in the order they are applied
+    pub fn actions(input: &Input) -> Vec<EditorAction> {
+        let mut actions = Vec::new();
+
+        // A long frame can fire several repeats, each one moves the cursor again
+        let cursor_moves = [
+            (Action::CursorUp, (0, -1)),
+            (Action::CursorDown, (0, 1)),
+            (Action::CursorLeft, (-1, 0)),
+            (Action::CursorRight, (1, 0)),
+        ];
+        for &(action, (x, y)) in cursor_moves.iter() {
+            for _ in 0..input.repeats(action) {
+                actions.push(EditorAction::MoveCursor(x, y));
+            }
+        }
+
+        if input.pressed(Action::SaveMap) {
+            actions.push(EditorAction::SaveMap);
+        }
+
+        for _ in 0..input.repeats(Action::RaiseTile) {
+            actions.push(EditorAction::RaiseTile);
+        }
+        for _ in 0..input.repeats(Action::LowerTile) {
+            actions.push(EditorAction::LowerTile);
+        }
+        if input.pressed(Action::SwapModel) {
+            actions.push(EditorAction::SwapModel);
+        }
+        if input.pressed(Action::SwapMaterial) {
+            actions.push(EditorAction::SwapMaterial);
+        }
+        if input.pressed(Action::SwapPassability) {
+            actions.push(EditorAction::SwapPassability);
+        }
+        if input.pressed(Action::SwapZone) {
+            actions.push(EditorAction::SwapZone);
+        }
+        if input.pressed(Action::RotateTile) {
+            actions.push(EditorAction::RotateTile);
+        }
+        if input.pressed(Action::SetSpawn) {
+            actions.push(EditorAction::SetSpawn);
+        }
+        if input.pressed(Action::ShowPassability) {
+            actions.push(EditorAction::ShowPassability);
+        }
+
+        actions
+    }
+
+    pub fn update(&mut self, input: &Input) -> Result<(), String> {
+        for action in Self::actions(input) {
+            self.apply(action)?;
+        }
+        Ok(())
+    }
+
+    /// The serialized map, for comparing the result of a replay against a known good one
+    pub fn snapshot(&self) -> Vec<u8> {
+        nanoserde::SerBin::serialize_bin(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor() -> Editor {
+        Editor::new(Map::new(), "unused.blob")
+    }
+
+    fn apply_all(editor: &mut Editor, actions: &[EditorAction]) {
+        for action in actions.iter() {
+            editor.apply(*action).unwrap();
+        }
+    }
+
+    #[test]
+    fn tile_height_stops_at_the_ends() {
+        let mut editor = editor();
+        editor.apply(EditorAction::LowerTile).unwrap();
+        assert_eq!(editor.selected_tile().unwrap().height, 0);
+
+        apply_all(&mut editor, &[EditorAction::RaiseTile; 300]);
+        assert_eq!(editor.selected_tile().unwrap().height, u8::MAX);
+
+        editor.apply(EditorAction::LowerTile).unwrap();
+        assert_eq!(editor.selected_tile().unwrap().height, u8::MAX - 1);
+    }
+
+    #[test]
+    fn spawn_is_set_to_the_cursor() {
+        let mut editor = editor();
+        apply_all(
+            &mut editor,
+            &[
+                EditorAction::MoveCursor(4, 0),
+                EditorAction::MoveCursor(0, 7),
+                EditorAction::SetSpawn,
+                EditorAction::MoveCursor(1, 1),
+            ],
+        );
+        assert_eq!(editor.map.spawn, (4, 7));
+    }
+
+    #[test]
+    fn zones_wrap_after_the_last() {
+        let mut editor = editor();
+        for zone in 1..=Tile::MAX_ZONE {
+            editor.apply(EditorAction::SwapZone).unwrap();
+            assert_eq!(editor.selected_tile().unwrap().zone, zone);
+        }
+        editor.apply(EditorAction::SwapZone).unwrap();
+        assert_eq!(editor.selected_tile().unwrap().zone, 0);
+    }
+
+    #[test]
+    fn actions_off_the_map_do_nothing() {
+        let mut editor = editor();
+        apply_all(
+            &mut editor,
+            &[
+                EditorAction::MoveCursor(-1, 0),
+                EditorAction::RaiseTile,
+                EditorAction::SwapZone,
+            ],
+        );
+        assert!(editor.selected_tile().is_none());
+        assert_eq!(editor.snapshot(), Editor::new(Map::new(), "").snapshot());
+    }
+}
@@ -0,0 +1,553 @@
+//! Wild monster encounters. Designers paint zones onto tiles in the editor and the
+//! encounter file says which table each zone rolls on, for example
+//!
+//! ```ron
+//! (
+//!     zones: [
+//!         (zone: 1, table: "route-1", rate: 0.1),
+//!     ],
+//!     tables: [
+//!         (
+//!             name: "route-1",
+//!             entries: [
+//!                 (species: "Sproutle", min_level: 2, max_level: 4, weight: 40),
+//!                 (species: "Hootwing", min_level: 3, max_level: 5, weight: 10, time: "Night"),
+//!             ],
+//!         ),
+//!     ],
+//! )
+//! ```
+
+use super::data::{quoted, Source};
+use super::map::Tile;
+use super::rng::Rng;
+use super::species::SpeciesRegistry;
+use nanoserde::DeRon;
+use std::collections::HashMap;
+
+#[derive(DeRon)]
+struct EntryConfig {
+    species: String,
+    min_level: u8,
+    max_level: u8,
+    weight: u32,
+    time: Option<String>,
+}
+
+#[derive(DeRon)]
+struct TableConfig {
+    name: String,
+    entries: Vec<EntryConfig>,
+}
+
+#[derive(DeRon)]
+struct ZoneConfig {
+    zone: u8,
+    table: String,
+    rate: f32,
+}
+
+#[derive(DeRon)]
+struct EncountersConfig {
+    zones: Vec<ZoneConfig>,
+    tables: Vec<TableConfig>,
+}
+
+/// Part of the in-game day, encounter entries can be limited to one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Morning,
+    Day,
+    Night,
+}
+
+impl TimeOfDay {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Morning" => Some(TimeOfDay::Morning),
+            "Day" => Some(TimeOfDay::Day),
+            "Night" => Some(TimeOfDay::Night),
+            _ => None,
+        }
+    }
+
+    /// Morning is from 4 to 10, day until 20 and night the rest
+    pub fn from_hour(hour: f32) -> Self {
+        let hour = hour.rem_euclid(24.0);
+        if (4.0..10.0).contains(&hour) {
+            TimeOfDay::Morning
+        } else if (10.0..20.0).contains(&hour) {
+            TimeOfDay::Day
+        } else {
+            TimeOfDay::Night
+        }
+    }
+}
+
+/// One kind of monster that can appear from a table
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncounterEntry {
+    pub species: String,
+    pub min_level: u8,
+    pub max_level: u8,
+    /// Chance of being picked relative to the other entries available at the time
+    pub weight: u32,
+    /// Only appears at this time of day, any time when `None`
+    pub time: Option<TimeOfDay>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncounterTable {
+    pub name: String,
+    pub entries: Vec<EncounterEntry>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Zone {
+    /// Index into [`Encounters::tables`]
+    pub table: usize,
+    /// Chance of an encounter on each step, from 0 to 1
+    pub rate: f32,
+}
+
+/// A monster that jumped out of the grass
+#[derive(Clone, Debug, PartialEq)]
+pub struct WildEncounter {
+    pub species: String,
+    pub level: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Encounters {
+    pub tables: Vec<EncounterTable>,
+    /// Keyed by the zone painted on the tiles, see [`Tile::zone`](super::map::Tile::zone)
+    pub zones: HashMap<u8, Zone>,
+}
+
+impl Encounters {
+    /// Next to the map it belongs to
+    pub const PATH: &'static str = "encounters.ron";
+
+    /// A missing file means there are no encounters anywhere. Every mistake in the file
+    /// is reported with its line.
+    pub fn load(path: &str, species: &SpeciesRegistry) -> Result<Self, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+        let source = Source {
+            path: path.to_string(),
+            text,
+        };
+
+        let mut errors = Vec::new();
+        let encounters = Self::from_source(source, species, &mut errors);
+        if errors.is_empty() {
+            Ok(encounters)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Every species the tables name has to be in `species`. Entries and zones with
+    /// mistakes are left out after adding them to `errors`.
+    pub fn from_source(
+        source: Source,
+        species: &SpeciesRegistry,
+        errors: &mut Vec<String>,
+    ) -> Self {
+        let (source, config) = match source.parsed::<EncountersConfig>(errors) {
+            Some(parsed) => parsed,
+            None => return Self::default(),
+        };
+
+        let mut tables: Vec<EncounterTable> = Vec::with_capacity(config.tables.len());
+        for table in config.tables {
+            let name = format!("name: {}", quoted(&table.name));
+            if tables.iter().any(|other| other.name == table.name) {
+                errors.push(
+                    source
+                        .duplicate_error(&name, format!("table `{}` is defined twice", table.name)),
+                );
+                continue;
+            }
+            if table.entries.is_empty() {
+                errors.push(source.error(&name, format!("table `{}` has no entries", table.name)));
+            }
+
+            let mut entries = Vec::with_capacity(table.entries.len());
+            for entry in &table.entries {
+                let mut valid = true;
+                let mut error = |message: String| {
+                    valid = false;
+                    errors.push(source.error_after(
+                        &name,
+                        &quoted(&entry.species),
+                        format!("table `{}`: {}", table.name, message),
+                    ));
+                };
+
+                if species.find(&entry.species).is_none() {
+                    error(format!("unknown species `{}`", entry.species));
+                }
+                if entry.min_level == 0
+                    || entry.min_level > entry.max_level
+                    || entry.max_level > SpeciesRegistry::MAX_LEVEL
+                {
+                    error(format!(
+                        "`{}` has an invalid level range {}..{}",
+                        entry.species, entry.min_level, entry.max_level
+                    ));
+                }
+                if entry.weight == 0 {
+                    error(format!("`{}` has a weight of 0", entry.species));
+                }
+                let time = match &entry.time {
+                    Some(name) => {
+                        let time = TimeOfDay::from_name(name);
+                        if time.is_none() {
+                            error(format!("unknown time of day `{}`", name));
+                        }
+                        time
+                    }
+                    None => None,
+                };
+
+                if valid {
+                    entries.push(EncounterEntry {
+                        species: entry.species.clone(),
+                        min_level: entry.min_level,
+                        max_level: entry.max_level,
+                        weight: entry.weight,
+                        time,
+                    });
+                }
+            }
+
+            // Kept even with no valid entries, so zones rolling on it aren't reported too
+            tables.push(EncounterTable {
+                name: table.name,
+                entries,
+            });
+        }
+
+        let mut zones = HashMap::new();
+        let mut defined = Vec::with_capacity(config.zones.len());
+        for zone in config.zones {
+            let needle = format!("zone: {},", zone.zone);
+            if defined.contains(&zone.zone) {
+                errors.push(
+                    source.duplicate_error(&needle, format!("zone {} is defined twice", zone.zone)),
+                );
+                continue;
+            }
+            defined.push(zone.zone);
+
+            let mut valid = true;
+            let mut error = |message: String| {
+                valid = false;
+                errors.push(source.error(&needle, message));
+            };
+
+            if zone.zone == 0 {
+                error("zone 0 means no zone and can't have a table".to_string());
+            }
+            if zone.zone > Tile::MAX_ZONE {
+                error(format!(
+                    "zone {} can't be painted, the last zone is {}",
+                    zone.zone,
+                    Tile::MAX_ZONE
+                ));
+            }
+            if !(0.0..=1.0).contains(&zone.rate) {
+                error(format!(
+                    "zone {}: rate {} isn't between 0 and 1",
+                    zone.zone, zone.rate
+                ));
+            }
+            let table = tables.iter().position(|table| table.name == zone.table);
+            if table.is_none() {
+                error(format!(
+                    "zone {}: unknown table `{}`",
+                    zone.zone, zone.table
+                ));
+            }
+
+            if let (true, Some(table)) = (valid, table) {
+                zones.insert(
+                    zone.zone,
+                    Zone {
+                        table,
+                        rate: zone.rate,
+                    },
+                );
+            }
+        }
+
+        Self { tables, zones }
+    }
+
+    /// Rolls for an encounter on a step into `zone`, picking from the entries
+    /// available at `time` by weight
+    pub fn roll(&self, zone: u8, time: TimeOfDay, rng: &mut Rng) -> Option<WildEncounter> {
+        let zone = self.zones.get(&zone)?;
+        if !rng.chance(zone.rate) {
+            return None;
+        }
+
+        let available: Vec<_> = self.tables[zone.table]
+            .entries
+            .iter()
+            .filter(|entry| entry.time.map_or(true, |entry_time| entry_time == time))
+            .collect();
+        let total: u32 = available.iter().map(|entry| entry.weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = rng.below(total);
+        let entry = available.into_iter().find(|entry| {
+            if pick < entry.weight {
+                true
+            } else {
+                pick -= entry.weight;
+                false
+            }
+        })?;
+
+        Some(WildEncounter {
+            species: entry.species.clone(),
+            level: rng.range(entry.min_level as u32, entry.max_level as u32) as u8,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::data::GameData;
+
+    /// Encounters from `text` with the shipped species, and what was wrong with them
+    fn load(text: &str) -> (Encounters, Vec<String>) {
+        let data = GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap();
+        let source = Source {
+            path: "encounters.ron".to_string(),
+            text: text.to_string(),
+        };
+        let mut errors = Vec::new();
+        let encounters = Encounters::from_source(source, &data.species, &mut errors);
+        (encounters, errors)
+    }
+
+    fn zones(zone: u8) -> String {
+        format!(
+            r#"(
+                zones: [(zone: {}, table: "route-1", rate: 0.1)],
+                tables: [(
+                    name: "route-1",
+                    entries: [(species: "Sproutle", min_level: 2, max_level: 4, weight: 40)],
+                )],
+            )"#,
+            zone
+        )
+    }
+
+    #[test]
+    fn zones_have_to_be_paintable() {
+        assert!(load(&zones(Tile::MAX_ZONE)).1.is_empty());
+        assert_eq!(
+            load(&zones(0)).1,
+            ["encounters.ron:2: zone 0 means no zone and can't have a table"]
+        );
+        assert_eq!(
+            load(&zones(Tile::MAX_ZONE + 1)).1,
+            ["encounters.ron:2: zone 16 can't be painted, the last zone is 15"]
+        );
+    }
+
+    #[test]
+    fn module_example_loads() {
+        let example = r#"(
+            zones: [
+                (zone: 1, table: "route-1", rate: 0.1),
+            ],
+            tables: [
+                (
+                    name: "route-1",
+                    entries: [
+                        (species: "Sproutle", min_level: 2, max_level: 4, weight: 40),
+                        (species: "Hootwing", min_level: 3, max_level: 5, weight: 10, time: "Night"),
+                    ],
+                ),
+            ],
+        )"#;
+        let (encounters, errors) = load(example);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(encounters.tables[0].entries.len(), 2);
+    }
+
+    #[test]
+    fn shipped_encounters_load() {
+        let data = GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../encounters.ron");
+        let encounters = Encounters::load(path, &data.species).unwrap();
+        assert!(!encounters.zones.is_empty());
+    }
+
+    #[test]
+    fn every_mistake_is_reported_on_its_line() {
+        let (encounters, errors) = load(
+            r#"(
+    zones: [
+        (zone: 0, table: "meadow", rate: 0.1),
+        (zone: 1, table: "swamp", rate: 1.5),
+        (zone: 2, table: "meadow", rate: 0.1),
+        (zone: 2, table: "meadow", rate: 0.1),
+    ],
+    tables: [
+        (
+            name: "meadow",
+            entries: [
+                (species: "Sproutle", min_level: 2, max_level: 4, weight: 40),
+                (species: "Mewtoo", min_level: 5, max_level: 3, weight: 0, time: "Dusk"),
+            ],
+        ),
+        (
+            name: "lakeside",
+            entries: [
+                (species: "Sproutle", min_level: 4, max_level: 2, weight: 1),
+            ],
+        ),
+        (name: "meadow", entries: []),
+        (name: "empty", entries: []),
+    ],
+)"#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "encounters.ron:13: table `meadow`: unknown species `Mewtoo`",
+                "encounters.ron:13: table `meadow`: `Mewtoo` has an invalid level range 5..3",
+                "encounters.ron:13: table `meadow`: `Mewtoo` has a weight of 0",
+                "encounters.ron:13: table `meadow`: unknown time of day `Dusk`",
+                "encounters.ron:19: table `lakeside`: `Sproutle` has an invalid level range 4..2",
+                "encounters.ron:22: table `meadow` is defined twice",
+                "encounters.ron:23: table `empty` has no entries",
+                "encounters.ron:3: zone 0 means no zone and can't have a table",
+                "encounters.ron:4: zone 1: rate 1.5 isn't between 0 and 1",
+                "encounters.ron:4: zone 1: unknown table `swamp`",
+                "encounters.ron:6: zone 2 is defined twice",
+            ]
+        );
+        // What's right is still loaded
+        assert_eq!(encounters.tables[0].entries.len(), 1);
+        assert_eq!(encounters.zones.keys().collect::<Vec<_>>(), [&2]);
+    }
+
+    /// One zone that always rolls on a table with `entries`
+    fn table(entries: &str) -> Encounters {
+        let (encounters, errors) = load(&format!(
+            r#"(
+                zones: [(zone: 1, table: "route-1", rate: 1.0)],
+                tables: [(name: "route-1", entries: [{}])],
+            )"#,
+            entries
+        ));
+        assert!(errors.is_empty(), "{:?}", errors);
+        encounters
+    }
+
+    /// The species of `count` rolls in zone 1 at `time`, or `None` when nothing appeared
+    fn roll_species(
+        encounters: &Encounters,
+        time: TimeOfDay,
+        count: usize,
+        rng: &mut Rng,
+    ) -> Vec<Option<String>> {
+        (0..count)
+            .map(|_| {
+                encounters
+                    .roll(1, time, rng)
+                    .map(|encounter| encounter.species)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_rolls_the_same_encounters() {
+        let encounters = table(
+            r#"(species: "Sproutle", min_level: 2, max_level: 4, weight: 40),
+               (species: "Hootwing", min_level: 3, max_level: 5, weight: 10)"#,
+        );
+        let roll = || {
+            let mut rng = Rng::new(5);
+            (0..100)
+                .map(|_| encounters.roll(1, TimeOfDay::Day, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(roll(), roll());
+    }
+
+    #[test]
+    fn entries_only_appear_at_their_time_of_day() {
+        let encounters = table(
+            r#"(species: "Sproutle", min_level: 2, max_level: 4, weight: 1, time: "Morning"),
+               (species: "Hootwing", min_level: 3, max_level: 5, weight: 1, time: "Night")"#,
+        );
+        let mut rng = Rng::new(6);
+
+        let morning = roll_species(&encounters, TimeOfDay::Morning, 100, &mut rng);
+        assert!(morning
+            .iter()
+            .all(|species| species.as_deref() == Some("Sproutle")));
+        let night = roll_species(&encounters, TimeOfDay::Night, 100, &mut rng);
+        assert!(night
+            .iter()
+            .all(|species| species.as_deref() == Some("Hootwing")));
+        // Nothing is out during the day
+        let day = roll_species(&encounters, TimeOfDay::Day, 100, &mut rng);
+        assert!(day.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn entries_are_picked_by_weight() {
+        let encounters = table(
+            r#"(species: "Sproutle", min_level: 2, max_level: 4, weight: 3),
+               (species: "Hootwing", min_level: 3, max_level: 5, weight: 1)"#,
+        );
+        let rolls = roll_species(&encounters, TimeOfDay::Day, 4000, &mut Rng::new(7));
+        let sproutles = rolls
+            .iter()
+            .filter(|species| species.as_deref() == Some("Sproutle"))
+            .count();
+        assert!((2850..=3150).contains(&sproutles), "{}", sproutles);
+    }
+
+    #[test]
+    fn levels_stay_in_the_entry_range() {
+        let encounters = table(r#"(species: "Sproutle", min_level: 3, max_level: 5, weight: 1)"#);
+        let mut rng = Rng::new(8);
+        let mut seen = [false; 3];
+        for _ in 0..200 {
+            let level = encounters.roll(1, TimeOfDay::Day, &mut rng).unwrap().level;
+            assert!((3..=5).contains(&level), "{}", level);
+            seen[level as usize - 3] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+    }
+
+    #[test]
+    fn only_zones_with_a_table_have_encounters() {
+        let encounters = table(r#"(species: "Sproutle", min_level: 2, max_level: 4, weight: 1)"#);
+        let mut rng = Rng::new(9);
+        for _ in 0..100 {
+            assert_eq!(encounters.roll(0, TimeOfDay::Day, &mut rng), None);
+            assert_eq!(encounters.roll(2, TimeOfDay::Day, &mut rng), None);
+            assert_eq!(
+                encounters.roll(Tile::MAX_ZONE, TimeOfDay::Day, &mut rng),
+                None
+            );
+        }
+    }
+}
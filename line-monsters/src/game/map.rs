@@ -0,0 +1,469 @@
+use super::world::Facing;
+use nanoserde::{DeBin, SerBin};
+use std::path::Path;
+
+#[derive(DeBin, SerBin, Copy, Clone, Debug, PartialEq)]
+pub enum Model {
+    Wall,
+    Corner,
+    InnerCorner,
+    Floor,
+}
+
+impl Model {
+    pub fn swap_model(&mut self) {
+        match self {
+            Model::Wall => *self = Model::Floor,
+            Model::Floor => *self = Model::Corner,
+            Model::Corner => *self = Model::InnerCorner,
+            Model::InnerCorner => *self = Model::Wall,
+        }
+    }
+}
+
+/// Counter clockwise
+#[derive(DeBin, SerBin, Copy, Clone, Debug, PartialEq)]
+pub enum TileRotation {
+    Zero,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl TileRotation {
+    pub fn rotate_next(&mut self) {
+        match self {
+            TileRotation::Zero => *self = TileRotation::Quarter,
+            TileRotation::Quarter => *self = TileRotation::Half,
+            TileRotation::Half => *self = TileRotation::ThreeQuarters,
+            TileRotation::ThreeQuarters => *self = TileRotation::Zero,
+        }
+    }
+}
+
+/// What the top of a tile is made of, picks the floor texture
+#[derive(DeBin, SerBin, Copy, Clone, Debug, PartialEq)]
+pub enum Material {
+    Grass,
+    TallGrass,
+    Water,
+    Lava,
+}
+
+impl Material {
+    pub fn swap_material(&mut self) {
+        match self {
+            Material::Grass => *self = Material::TallGrass,
+            Material::TallGrass => *self = Material::Water,
+            Material::Water => *self = Material::Lava,
+            Material::Lava => *self = Material::Grass,
+        }
+    }
+}
+
+/// What can move onto a tile, on top of what its model and height allow
+#[derive(DeBin, SerBin, Copy, Clone, Debug, PartialEq)]
+pub enum Passability {
+    Walkable,
+    /// Trees, signs and invisible walls
+    Blocked,
+    /// Water that can only be crossed by surfing
+    Surfable,
+    /// Walkable, and wild monsters can appear while walking through it
+    EncounterGrass,
+    /// Can only be entered moving in the ledge's direction, which jumps over it
+    LedgeUp,
+    LedgeDown,
+    LedgeLeft,
+    LedgeRight,
+}
+
+impl Passability {
+    pub fn swap_passability(&mut self) {
+        match self {
+            Passability::Walkable => *self = Passability::Blocked,
+            Passability::Blocked => *self = Passability::Surfable,
+            Passability::Surfable => *self = Passability::EncounterGrass,
+            Passability::EncounterGrass => *self = Passability::LedgeUp,
+            Passability::LedgeUp => *self = Passability::LedgeDown,
+            Passability::LedgeDown => *self = Passability::LedgeLeft,
+            Passability::LedgeLeft => *self = Passability::LedgeRight,
+            Passability::LedgeRight => *self = Passability::Walkable,
+        }
+    }
+
+    /// Whether a tile can be walked onto, ledges only from the right direction
+    pub fn is_walkable(self) -> bool {
+        matches!(self, Passability::Walkable | Passability::EncounterGrass)
+    }
+
+    /// The direction a ledge is jumped in
+    pub fn ledge(self) -> Option<Facing> {
+        match self {
+            Passability::LedgeUp => Some(Facing::Up),
+            Passability::LedgeDown => Some(Facing::Down),
+            Passability::LedgeLeft => Some(Facing::Left),
+            Passability::LedgeRight => Some(Facing::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(SerBin, Copy, Clone, Debug, PartialEq)]
+pub struct Tile {
+    pub height: u8,
+    pub model: Model,
+    pub rotation: TileRotation,
+    pub material: Material,
+    pub passability: Passability,
+    /// The encounter zone the tile is painted with, 0 for none. See
+    /// [`Encounters`](super::encounter::Encounters) for what each zone rolls.
+    pub zone: u8,
+}
+
+impl Tile {
+    pub fn new_rotation(height: u8, model: Model, rotation: TileRotation) -> Self {
+        Self {
+            height,
+            model,
+            rotation,
+            material: Material::Grass,
+            passability: Passability::Walkable,
+            zone: 0,
+        }
+    }
+
+    /// Zones are painted from 0 up to this
+    pub const MAX_ZONE: u8 = 15;
+
+    pub fn swap_zone(&mut self) {
+        self.zone = (self.zone + 1) % (Self::MAX_ZONE + 1);
+    }
+
+    /// Reads a tile saved by map format `version`, filling in fields older versions lack
+    fn de_bin_version(
+        o: &mut usize,
+        d: &[u8],
+        version: u32,
+    ) -> std::result::Result<Self, nanoserde::DeBinErr> {
+        std::result::Result::Ok(Self {
+            height: DeBin::de_bin(o, d)?,
+            model: DeBin::de_bin(o, d)?,
+            rotation: DeBin::de_bin(o, d)?,
+            material: if version >= 1 {
+                DeBin::de_bin(o, d)?
+            } else {
+                Material::Grass
+            },
+            passability: if version >= 3 {
+                DeBin::de_bin(o, d)?
+            } else {
+                Passability::Walkable
+            },
+            zone: if version >= 4 {
+                DeBin::de_bin(o, d)?
+            } else {
+                0
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    pub x: isize,
+    pub y: isize,
+    pub tiles: [Tile; Self::WIDTH * Self::HEIGHT],
+}
+
+impl DeBin for Chunk {
+    fn de_bin(o: &mut usize, d: &[u8]) -> std::result::Result<Self, nanoserde::DeBinErr> {
+        Self::de_bin_version(o, d, Map::VERSION)
+    }
+}
+impl SerBin for Chunk {
+    fn ser_bin(&self, s: &mut Vec<u8>) {
+        self.x.ser_bin(s);
+        self.y.ser_bin(s);
+        self.tiles.ser_bin(s);
+    }
+}
+
+impl Chunk {
+    pub const WIDTH: usize = 32;
+    pub const HEIGHT: usize = 32;
+
+    pub const I_WIDTH: isize = 32;
+    pub const I_HEIGHT: isize = 32;
+
+    pub fn new(x: isize, y: isize) -> Self {
+        Self {
+            x,
+            y,
+            tiles: [Tile::new_rotation(0, Model::Floor, TileRotation::Zero);
+                Self::WIDTH * Self::HEIGHT],
+        }
+    }
+
+    fn de_bin_version(
+        o: &mut usize,
+        d: &[u8],
+        version: u32,
+    ) -> std::result::Result<Self, nanoserde::DeBinErr> {
+        let x = DeBin::de_bin(o, d)?;
+        let y = DeBin::de_bin(o, d)?;
+
+        let mut chunk = Self::new(x, y);
+        for tile in chunk.tiles.iter_mut() {
+            *tile = Tile::de_bin_version(o, d, version)?;
+        }
+        std::result::Result::Ok(chunk)
+    }
+
+    /// Lowest and highest tile height in the chunk
+    pub fn height_range(&self) -> (u8, u8) {
+        self.tiles
+            .iter()
+            .fold((u8::MAX, u8::MIN), |(min, max), tile| {
+                (min.min(tile.height), max.max(tile.height))
+            })
+    }
+}
+
+#[derive(SerBin, Clone, Debug)]
+pub struct Map {
+    pub chunks: Vec<Chunk>,
+    /// The tile the player starts on in play mode
+    pub spawn: (isize, isize),
+}
+
+impl DeBin for Map {
+    fn de_bin(o: &mut usize, d: &[u8]) -> std::result::Result<Self, nanoserde::DeBinErr> {
+        Self::de_bin_version(o, d, Self::VERSION)
+    }
+}
+
+impl Map {
+    pub const PATH: &'static str = "map_data.blob";
+
+    /// Saved maps start with this, followed by the format version as a little endian `u32`.
+    /// Maps from before the header existed are read as version 0.
+    const MAGIC: &'static [u8; 4] = b"LMAP";
+    /// 1 added [`Tile::material`], 2 added [`Map::spawn`], 3 added [`Tile::passability`],
+    /// 4 added [`Tile::zone`]
+    const VERSION: u32 = 4;
+
+    /// A single flat chunk at the origin
+    pub fn new() -> Self {
+        Map {
+            chunks: vec![Chunk::new(0, 0)],
+            spawn: (0, 0),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_bytes(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The map as [`Map::save`] writes it, in the current format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        self.ser_bin(&mut bytes);
+        bytes
+    }
+
+    /// Reads a map saved in any format version up to the current one
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let (version, data) = if data.starts_with(Self::MAGIC) && data.len() >= 8 {
+            let mut version = [0; 4];
+            version.copy_from_slice(&data[4..8]);
+            (u32::from_le_bytes(version), &data[8..])
+        } else {
+            (0, data)
+        };
+        if version > Self::VERSION {
+            return Err(format!(
+                "map format {} is newer than this build supports ({})",
+                version,
+                Self::VERSION
+            ));
+        }
+
+        Self::de_bin_version(&mut 0, data, version).map_err(|e| format!("{:?}", e))
+    }
+
+    fn de_bin_version(
+        o: &mut usize,
+        d: &[u8],
+        version: u32,
+    ) -> std::result::Result<Self, nanoserde::DeBinErr> {
+        let len: usize = DeBin::de_bin(o, d)?;
+        let chunks = (0..len)
+            .map(|_| Chunk::de_bin_version(o, d, version))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let spawn = if version >= 2 {
+            DeBin::de_bin(o, d)?
+        } else {
+            (0, 0)
+        };
+        std::result::Result::Ok(Map { chunks, spawn })
+    }
+
+    pub fn xy_to_chunk_coord(x: isize, y: isize) -> (isize, isize, usize, usize) {
+        let chunk_x = f32::floor(x as f32 / 32.0) as isize;
+        let chunk_y = f32::floor(y as f32 / 32.0) as isize;
+
+        let (width, height) = (Chunk::I_WIDTH, Chunk::I_HEIGHT);
+        let x_pos = if x >= 0 {
+            x % width
+        } else {
+            width - 1 - isize::abs((x + 1) % width)
+        };
+        let y_pos = if y >= 0 {
+            y % height
+        } else {
+            height - 1 - isize::abs((y + 1) % height)
+        };
+
+        assert!(x_pos >= 0 && y_pos >= 0);
+
+        (chunk_x, chunk_y, x_pos as usize, y_pos as usize)
+    }
+
+    pub fn get_tile(&self, x: isize, y: isize) -> Option<&Tile> {
+        let (chunk_x, chunk_y, x, y) = Self::xy_to_chunk_coord(x, y);
+
+        let chunk = self
+            .chunks
+            .iter()
+            .find(|chunk| chunk.x == chunk_x && chunk.y == chunk_y)?;
+
+        Some(&chunk.tiles[y * 32 + x])
+    }
+
+    /// What can move onto the tile at `x`, `y`, `None` off the edge of the map
+    pub fn passability(&self, x: isize, y: isize) -> Option<Passability> {
+        self.get_tile(x, y).map(|tile| tile.passability)
+    }
+
+    pub fn get_tile_mut(&mut self, x: isize, y: isize) -> Option<&mut Tile> {
+        let (chunk_x, chunk_y, x, y) = Self::xy_to_chunk_coord(x, y);
+
+        let chunk = self
+            .chunks
+            .iter_mut()
+            .find(|chunk| chunk.x == chunk_x && chunk.y == chunk_y)?;
+
+        Some(&mut chunk.tiles[y * 32 + x])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A map in the layout format `version` saved, built field by field
+    fn saved_as(version: u32, map: &Map) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if version > 0 {
+            bytes.extend_from_slice(Map::MAGIC);
+            bytes.extend_from_slice(&version.to_le_bytes());
+        }
+        map.chunks.len().ser_bin(&mut bytes);
+        for chunk in map.chunks.iter() {
+            chunk.x.ser_bin(&mut bytes);
+            chunk.y.ser_bin(&mut bytes);
+            for tile in chunk.tiles.iter() {
+                tile.height.ser_bin(&mut bytes);
+                tile.model.ser_bin(&mut bytes);
+                tile.rotation.ser_bin(&mut bytes);
+                if version >= 1 {
+                    tile.material.ser_bin(&mut bytes);
+                }
+                if version >= 3 {
+                    tile.passability.ser_bin(&mut bytes);
+                }
+                if version >= 4 {
+                    tile.zone.ser_bin(&mut bytes);
+                }
+            }
+        }
+        if version >= 2 {
+            map.spawn.ser_bin(&mut bytes);
+        }
+        bytes
+    }
+
+    fn sample_map() -> Map {
+        let mut map = Map::new();
+        map.chunks.push(Chunk::new(-1, 2));
+        map.spawn = (3, 4);
+        let tile = map.get_tile_mut(5, 6).unwrap();
+        tile.height = 7;
+        tile.model = Model::Wall;
+        tile.rotation = TileRotation::Half;
+        tile.material = Material::Lava;
+        tile.passability = Passability::LedgeLeft;
+        tile.zone = 9;
+        map
+    }
+
+    #[test]
+    fn loads_every_older_format() {
+        let map = sample_map();
+        for version in 0..Map::VERSION {
+            let loaded = Map::from_bytes(&saved_as(version, &map)).unwrap();
+            let tile = loaded.get_tile(5, 6).unwrap();
+            assert_eq!(loaded.chunks.len(), 2, "version {}", version);
+            assert_eq!(loaded.chunks[1].x, -1, "version {}", version);
+            assert_eq!(loaded.chunks[1].y, 2, "version {}", version);
+            assert_eq!(
+                (tile.height, tile.model, tile.rotation),
+                (7, Model::Wall, TileRotation::Half),
+                "version {}",
+                version
+            );
+
+            let material = if version >= 1 {
+                Material::Lava
+            } else {
+                Material::Grass
+            };
+            let spawn = if version >= 2 { (3, 4) } else { (0, 0) };
+            let passability = if version >= 3 {
+                Passability::LedgeLeft
+            } else {
+                Passability::Walkable
+            };
+            assert_eq!(tile.material, material, "version {}", version);
+            assert_eq!(loaded.spawn, spawn, "version {}", version);
+            assert_eq!(tile.passability, passability, "version {}", version);
+            assert_eq!(tile.zone, 0, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn current_format_round_trips() {
+        let map = sample_map();
+        let bytes = map.to_bytes();
+        assert_eq!(bytes, saved_as(Map::VERSION, &map));
+
+        let loaded = Map::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.spawn, map.spawn);
+        assert_eq!(loaded.get_tile(5, 6), map.get_tile(5, 6));
+        assert_eq!(loaded.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn newer_formats_are_rejected() {
+        let mut bytes = sample_map().to_bytes();
+        bytes[4..8].copy_from_slice(&(Map::VERSION + 1).to_le_bytes());
+        assert!(Map::from_bytes(&bytes).is_err());
+    }
+}
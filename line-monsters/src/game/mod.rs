@@ -1,56 +1,55 @@
-use crate::renderer::texture;
+pub mod ai;
+pub mod battle;
+pub mod data;
+pub mod editor;
+pub mod encounter;
+pub mod map;
+pub mod monster;
+pub mod moves;
+// Nothing follows paths yet, NPCs and click-to-move will
+#[allow(dead_code)]
+pub mod path;
+pub mod play;
+pub mod player;
+pub mod rng;
+pub mod settings;
+pub mod simulation;
+pub mod species;
+pub mod trainer;
+pub mod types;
+pub mod walk;
+pub mod world;
+
+use crate::renderer::assets::{AssetManager, Mesh};
+use crate::renderer::material::TextureAnimation;
+use crate::renderer::spritebatch::{self, BlendMode, Spritebatch};
+use crate::renderer::texture::{self, TextureOptions};
 use crate::renderer::State;
-use crate::renderer::{spritebatch::Spritebatch, Vertex};
-use nanoserde::{DeBin, SerBin};
+use crate::renderer::Vertex;
+use map::{Chunk, Map, Material, Model, Passability, Tile, TileRotation};
 use std::sync::Arc;
-use wgpu::{Device, Queue};
-use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
-
-#[derive(DeBin, SerBin, Copy, Clone, Debug)]
-pub enum Model {
-    Wall,
-    Corner,
-    InnerCorner,
-    Floor,
-}
+use ultraviolet::Vec3;
+use wgpu::Device;
+use world::World;
 
 impl Model {
-    fn get_model(&self) -> (&'static [Vertex], &'static [u16]) {
-        match &self {
-            Model::Wall => (crate::models::wall::VERTS, crate::models::wall::INDICES),
-            Model::Floor => (crate::models::floor::VERTS, crate::models::floor::INDICES),
-            Model::Corner => (crate::models::corner::VERTS, crate::models::corner::INDICES),
-            Model::InnerCorner => (
-                crate::models::inner_corner::VERTS,
-                crate::models::inner_corner::INDICES,
-            ),
-        }
-    }
-
-    fn swap_model(&mut self) {
+    /// The mesh in the assets directory
+    fn mesh_path(self) -> &'static str {
         match self {
-            Model::Wall => *self = Model::Floor,
-            Model::Floor => *self = Model::Corner,
-            Model::Corner => *self = Model::InnerCorner,
-            Model::InnerCorner => *self = Model::Wall,
+            Model::Wall => "models/wall.ron",
+            Model::Floor => "models/floor.ron",
+            Model::Corner => "models/corner.ron",
+            Model::InnerCorner => "models/inner-corner.ron",
         }
     }
 }
 
-/// Counter clockwise
-#[derive(DeBin, SerBin, Copy, Clone, Debug)]
-pub enum TileRotation {
-    Zero,
-    Quarter,
-    Half,
-    ThreeQuarters,
-}
-
 impl TileRotation {
     fn rotate_vertice(&self, vertice: &Vertex) -> Vertex {
         let rotate_90 = |vertex: Vertex| Vertex {
             position: [vertex.position[2], vertex.position[1], -vertex.position[0]],
             tex_coords: vertex.tex_coords,
+            normal: [vertex.normal[2], vertex.normal[1], -vertex.normal[0]],
         };
 
         match self {
@@ -60,336 +59,188 @@ impl TileRotation {
             TileRotation::ThreeQuarters => rotate_90(rotate_90(rotate_90(*vertice))),
         }
     }
-
-    fn rotate_next(&mut self) {
-        match self {
-            TileRotation::Zero => *self = TileRotation::Quarter,
-            TileRotation::Quarter => *self = TileRotation::Half,
-            TileRotation::Half => *self = TileRotation::ThreeQuarters,
-            TileRotation::ThreeQuarters => *self = TileRotation::Zero,
-        }
-    }
 }
 
-#[derive(DeBin, SerBin, Copy, Clone, Debug)]
-pub struct Tile {
-    height: u8,
-    model: Model,
-    rotation: TileRotation,
-}
-
-impl Tile {
-    fn new_rotation(height: u8, model: Model, rotation: TileRotation) -> Self {
-        Self {
-            height,
-            model,
-            rotation,
+impl Passability {
+    /// Which of the side by side cells of the overlay texture shows this
+    fn overlay_cell(self) -> usize {
+        match self {
+            Passability::Walkable => 0,
+            Passability::Blocked => 1,
+            Passability::Surfable => 2,
+            Passability::EncounterGrass => 3,
+            Passability::LedgeUp => 4,
+            Passability::LedgeDown => 5,
+            Passability::LedgeLeft => 6,
+            Passability::LedgeRight => 7,
         }
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Chunk {
-    pub x: isize,
-    pub y: isize,
-    pub tiles: [Tile; Self::WIDTH * Self::HEIGHT],
+/// How many chunks survived culling in the last [`Scene::tick`]
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct ChunkStats {
+    pub drawn: usize,
+    pub total: usize,
 }
 
-impl DeBin for Chunk {
-    fn de_bin(o: &mut usize, d: &[u8]) -> std::result::Result<Self, nanoserde::DeBinErr> {
-        std::result::Result::Ok(Self {
-            x: DeBin::de_bin(o, d)?,
-            y: DeBin::de_bin(o, d)?,
-            tiles: DeBin::de_bin(o, d)?,
-        })
-    }
-}
-impl SerBin for Chunk {
-    fn ser_bin(&self, s: &mut Vec<u8>) {
-        self.x.ser_bin(s);
-        self.y.ser_bin(s);
-        self.tiles.ser_bin(s);
-    }
+/// World space bounds of everything a chunk draws. Models are centred on their tile
+/// and span half a tile either side, plus the raise on the selected tile.
+fn chunk_bounds(chunk: &Chunk) -> (Vec3, Vec3) {
+    let (min_height, max_height) = chunk.height_range();
+
+    let min = Vec3::new(
+        (chunk.x * Chunk::I_WIDTH) as f32 - 0.5,
+        min_height as f32 - 0.5,
+        (chunk.y * Chunk::I_HEIGHT) as f32 - 0.5,
+    );
+    let max = Vec3::new(
+        ((chunk.x + 1) * Chunk::I_WIDTH) as f32 - 0.5,
+        max_height as f32 + 0.75,
+        ((chunk.y + 1) * Chunk::I_HEIGHT) as f32 - 0.5,
+    );
+    (min, max)
 }
 
-impl Chunk {
-    const WIDTH: usize = 32;
-    const HEIGHT: usize = 32;
-
-    const I_WIDTH: isize = 32;
-    const I_HEIGHT: isize = 32;
-
-    fn new(x: isize, y: isize) -> Self {
-        Self {
-            x,
-            y,
-            tiles: [Tile::new_rotation(0, Model::Floor, TileRotation::Zero);
-                Self::WIDTH * Self::HEIGHT],
-        }
-    }
-}
-
-#[derive(DeBin, SerBin)]
-pub struct Map(Vec<Chunk>);
-
+/// Draws the map and the entities in the [`World`] on top of it, all editing happens
+/// in [`editor::Editor`]
 pub struct Scene {
     spritebatch: Spritebatch,
     grass_texture: Arc<texture::Texture>,
+    tall_grass_texture: Arc<texture::Texture>,
+    water_texture: Arc<texture::Texture>,
+    lava_texture: Arc<texture::Texture>,
     ground_wall_texture: Arc<texture::Texture>,
+    passability_texture: Arc<texture::Texture>,
+    /// Indexed by [`Scene::MODELS`]
+    meshes: Vec<Arc<Mesh>>,
 
-    move_select_left: bool,
-    move_select_right: bool,
-    move_select_up: bool,
-    move_select_down: bool,
-
-    save_map_return: bool,
-
-    raise: bool,
-    lower: bool,
+    /// Draws every tile's [`Passability`] over the map in colours
+    pub show_passability: bool,
 
-    swap_model: bool,
-
-    rotate_tile: bool,
-
-    selected: (isize, isize),
-
-    chunks: Map,
+    /// [`AssetManager::generation`] the textures and meshes were fetched at
+    assets_generation: u64,
 }
 
 impl Scene {
-    pub fn new(device: Arc<Device>, queue: &Queue) -> Self {
-        let spritebatch = Spritebatch::new(device.clone());
-
-        let grass_bytes = include_bytes!("../res/grass.png");
-        let grass_texture = texture::Texture::from_bytes(&device, &queue, grass_bytes, "grass.png");
-
-        let ground_wall_bytes = include_bytes!("../res/ground-wall.png");
-        let ground_wall_texture =
-            texture::Texture::from_bytes(&device, &queue, ground_wall_bytes, "ground-wall.png");
-
-        let chunks = Vec::new();
-
-        let mut scene = Self {
+    const GRASS_TEXTURE: &'static str = "textures/grass.png";
+    const TALL_GRASS_TEXTURE: &'static str = "textures/tall-grass.png";
+    const WATER_TEXTURE: &'static str = "textures/water.png";
+    const LAVA_TEXTURE: &'static str = "textures/lava.png";
+    const GROUND_WALL_TEXTURE: &'static str = "textures/ground-wall.png";
+    const PASSABILITY_TEXTURE: &'static str = "textures/passability.png";
+    /// Cells side by side in the passability texture
+    const PASSABILITY_CELLS: usize = 8;
+    const MODELS: [Model; 4] = [Model::Wall, Model::Floor, Model::Corner, Model::InnerCorner];
+
+    pub fn new(device: Arc<Device>, assets: &mut AssetManager) -> Result<Self, String> {
+        let spritebatch = Spritebatch::new(device);
+
+        Ok(Self {
             spritebatch,
-            grass_texture,
-            ground_wall_texture,
-
-            move_select_down: false,
-            move_select_left: false,
-            move_select_right: false,
-            move_select_up: false,
-
-            save_map_return: false,
-
-            raise: false,
-            lower: false,
-
-            swap_model: false,
-
-            rotate_tile: false,
-
-            selected: (0, 0),
-
-            chunks: Map(chunks),
-        };
-
-        scene.deserialize();
-
-        scene
-    }
-
-    pub fn serialize(&self) {
-        use std::fs::File;
-        use std::io::prelude::*;
-        use std::path::Path;
-
-        let bytes = SerBin::serialize_bin(&self.chunks);
-
-        let path = Path::new("map_data.blob");
-
-        let mut file = File::create(&path).unwrap();
-
-        file.write_all(&bytes).unwrap();
-    }
-
-    pub fn deserialize(&mut self) {
-        use std::fs::File;
-        use std::io::prelude::*;
-        use std::path::Path;
-
-        let path = Path::new("map_data.blob");
-
-        let mut file = File::open(&path).unwrap();
-
-        let mut data = Vec::with_capacity(std::mem::size_of::<Chunk>());
-        file.read_to_end(&mut data).unwrap();
-
-        let map: Map = DeBin::deserialize_bin(&data).unwrap();
-        self.chunks = map;
-    }
-
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        state,
-                        virtual_keycode: Some(keycode),
-                        ..
+            grass_texture: assets.texture(Self::GRASS_TEXTURE)?,
+            tall_grass_texture: assets.texture_with(
+                Self::TALL_GRASS_TEXTURE,
+                TextureOptions {
+                    animation: TextureAnimation::Frames {
+                        count: 4,
+                        duration: 0.3,
                     },
-                ..
-            } => {
-                let is_pressed = *state == ElementState::Pressed;
-                match keycode {
-                    VirtualKeyCode::Up => {
-                        self.move_select_up = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Left => {
-                        self.move_select_left = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Down => {
-                        self.move_select_down = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Right => {
-                        self.move_select_right = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Return => {
-                        self.save_map_return = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Space => {
-                        self.raise = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::C => {
-                        self.lower = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::X => {
-                        self.swap_model = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::R => {
-                        self.rotate_tile = is_pressed;
-                        true
-                    }
-                    _ => false,
-                }
-            }
-            _ => false,
-        }
+                    ..TextureOptions::PIXEL_ART
+                },
+            )?,
+            water_texture: assets.texture_with(
+                Self::WATER_TEXTURE,
+                TextureOptions {
+                    animation: TextureAnimation::Frames {
+                        count: 4,
+                        duration: 0.25,
+                    },
+                    ..TextureOptions::PIXEL_ART
+                },
+            )?,
+            lava_texture: assets.texture_with(
+                Self::LAVA_TEXTURE,
+                TextureOptions {
+                    animation: TextureAnimation::Scroll { speed: [0.1, 0.05] },
+                    ..TextureOptions::PIXEL_ART
+                },
+            )?,
+            ground_wall_texture: assets.texture(Self::GROUND_WALL_TEXTURE)?,
+            passability_texture: assets.texture(Self::PASSABILITY_TEXTURE)?,
+            meshes: Self::load_meshes(assets)?,
+            show_passability: false,
+            assets_generation: assets.generation(),
+        })
     }
 
-    pub fn xy_to_chunk_coord(x: isize, y: isize) -> (isize, isize, usize, usize) {
-        let chunk_x = f32::floor(x as f32 / 32.0) as isize;
-        let chunk_y = f32::floor(y as f32 / 32.0) as isize;
-
-        let (width, height) = (Chunk::I_WIDTH, Chunk::I_HEIGHT);
-        let x_pos = if x >= 0 {
-            x % width
-        } else {
-            width - 1 - isize::abs((x + 1) % width)
-        };
-        let y_pos = if y >= 0 {
-            y % height
-        } else {
-            height - 1 - isize::abs((y + 1) % height)
-        };
-
-        assert!(x_pos >= 0 && y_pos >= 0);
-
-        (chunk_x, chunk_y, x_pos as usize, y_pos as usize)
+    fn load_meshes(assets: &mut AssetManager) -> Result<Vec<Arc<Mesh>>, String> {
+        Self::MODELS
+            .iter()
+            .map(|model| assets.mesh(model.mesh_path()))
+            .collect()
     }
 
-    pub fn get_tile(&mut self, x: isize, y: isize) -> Option<&mut Tile> {
-        let (chunk_x, chunk_y, x, y) = Self::xy_to_chunk_coord(x, y);
-
-        let chunk = self
-            .chunks
-            .0
-            .iter_mut()
-            .find(|chunk| chunk.x == chunk_x && chunk.y == chunk_y)?;
-
-        Some(&mut chunk.tiles[(y * 32 + x) as usize])
+    fn mesh(&self, model: Model) -> Arc<Mesh> {
+        let index = Self::MODELS.iter().position(|other| *other == model);
+        self.meshes[index.expect("every model has a mesh")].clone()
     }
 
-    pub fn tick(&mut self, state: &mut State) {
-        if self.move_select_up {
-            self.selected.1 -= 1;
-        }
-        if self.move_select_down {
-            self.selected.1 += 1;
-        }
-
-        if self.move_select_left {
-            self.selected.0 -= 1;
-        }
-        if self.move_select_right {
-            self.selected.0 += 1;
-        }
-        self.move_select_up = false;
-        self.move_select_down = false;
-        self.move_select_left = false;
-        self.move_select_right = false;
-
-        if self.save_map_return {
-            self.serialize();
-        }
-        self.save_map_return = false;
-
-        if self.raise {
-            if let Some(tile) = self.get_tile(self.selected.0, self.selected.1) {
-                if tile.height != u8::MAX {
-                    tile.height += 1;
-                }
-            }
-        }
-
-        if self.lower {
-            if let Some(tile) = self.get_tile(self.selected.0, self.selected.1) {
-                if tile.height > 0 {
-                    tile.height -= 1;
-                }
-            }
+    /// Picks up textures and meshes the asset manager reloaded since the last tick
+    fn refresh_assets(&mut self, assets: &mut AssetManager) -> Result<(), String> {
+        if self.assets_generation == assets.generation() {
+            return Ok(());
         }
 
-        self.raise = false;
-        self.lower = false;
+        self.grass_texture = assets.texture(Self::GRASS_TEXTURE)?;
+        self.tall_grass_texture = assets.texture(Self::TALL_GRASS_TEXTURE)?;
+        self.water_texture = assets.texture(Self::WATER_TEXTURE)?;
+        self.lava_texture = assets.texture(Self::LAVA_TEXTURE)?;
+        self.ground_wall_texture = assets.texture(Self::GROUND_WALL_TEXTURE)?;
+        self.passability_texture = assets.texture(Self::PASSABILITY_TEXTURE)?;
+        self.meshes = Self::load_meshes(assets)?;
+        self.assets_generation = assets.generation();
+        Ok(())
+    }
 
-        if self.swap_model {
-            if let Some(tile) = self.get_tile(self.selected.0, self.selected.1) {
-                tile.model.swap_model();
-            }
+    fn material_texture(&self, material: Material) -> Arc<texture::Texture> {
+        match material {
+            Material::Grass => self.grass_texture.clone(),
+            Material::TallGrass => self.tall_grass_texture.clone(),
+            Material::Water => self.water_texture.clone(),
+            Material::Lava => self.lava_texture.clone(),
         }
-        self.swap_model = false;
+    }
 
-        if self.rotate_tile {
-            if let Some(tile) = self.get_tile(self.selected.0, self.selected.1) {
-                tile.rotation.rotate_next();
-            }
+    /// `cursor` is the tile raised to show the editor's selection, `world` is drawn when
+    /// there is one, after its animated sprites are set to their current frames
+    pub fn tick(
+        &mut self,
+        state: &mut State,
+        map: &Map,
+        cursor: Option<(isize, isize)>,
+        world: Option<&mut World>,
+    ) -> ChunkStats {
+        if let Err(e) = self.refresh_assets(&mut state.assets) {
+            eprintln!("Failed to refresh assets: {}", e);
         }
-        self.rotate_tile = false;
 
-        fn produce_verts(
+        fn produce_verts<'a>(
             tile: &Tile,
             (x, y): (isize, isize),
             rotation: TileRotation,
-            (selected_x, selected_y): (isize, isize),
-            model: Model,
-        ) -> (Vec<Vertex>, &'static [u16]) {
-            let (vertices, indices) = model.get_model();
-            let vertices: Vec<_> = vertices
+            cursor: Option<(isize, isize)>,
+            mesh: &'a Mesh,
+        ) -> (Vec<Vertex>, &'a [u16]) {
+            let vertices: Vec<_> = mesh
+                .vertices
                 .iter()
                 .map(|vertex| rotation.rotate_vertice(vertex))
                 .map(|vertex| Vertex {
                     position: [
                         vertex.position[0] + x as f32,
                         vertex.position[1] + tile.height as f32 + {
-                            if selected_x == x && selected_y == y {
+                            if cursor == Some((x, y)) {
                                 0.25
                             } else {
                                 0.0
@@ -398,28 +249,32 @@ impl Scene {
                         vertex.position[2] + y as f32,
                     ],
                     tex_coords: vertex.tex_coords,
+                    normal: vertex.normal,
                 })
                 .collect();
-            (vertices, indices)
+            (vertices, &mesh.indices)
         }
 
-        for chunk in self.chunks.0.iter() {
+        let floor = self.mesh(Model::Floor);
+        let camera = &state.camera;
+        let frustum = camera.frustum();
+
+        let mut stats = ChunkStats {
+            drawn: 0,
+            total: map.chunks.len(),
+        };
+
+        for chunk in map.chunks.iter().filter(|chunk| {
+            let (min, max) = chunk_bounds(chunk);
+            frustum.intersects_aabb(min, max) && camera.within_draw_distance(min, max)
+        }) {
+            stats.drawn += 1;
             let map = &chunk.tiles;
 
             for (x, y, tile) in map
                 .iter()
                 .enumerate()
-                .filter(|(_, tile)| {
-                    if let Model::Floor = tile.model {
-                        true
-                    } else {
-                        if let Model::Corner = tile.model {
-                            true
-                        } else {
-                            false
-                        }
-                    }
-                })
+                .filter(|(_, tile)| matches!(tile.model, Model::Floor | Model::Corner))
                 .map(|(n, tile)| {
                     (
                         (n as isize % Chunk::I_WIDTH) + chunk.x * Chunk::I_WIDTH, // x
@@ -429,22 +284,17 @@ impl Scene {
                 })
             {
                 let (vertices, indices) =
-                    produce_verts(tile, (x, y), tile.rotation, self.selected, Model::Floor);
-                let texture = self.grass_texture.clone();
+                    produce_verts(tile, (x, y), tile.rotation, cursor, &floor);
+                let texture = self.material_texture(tile.material);
 
-                self.spritebatch.push_verts(&vertices, indices, texture);
+                self.spritebatch
+                    .push_verts(&vertices, indices, texture, BlendMode::Opaque);
             }
 
             for (x, y, tile) in map
                 .iter()
                 .enumerate()
-                .filter(|(_, tile)| {
-                    if let Model::Floor = tile.model {
-                        false
-                    } else {
-                        true
-                    }
-                })
+                .filter(|(_, tile)| tile.model != Model::Floor)
                 .map(|(n, tile)| {
                     (
                         (n as isize % Chunk::I_WIDTH) + chunk.x * Chunk::I_WIDTH, // x
@@ -453,16 +303,84 @@ impl Scene {
                     )
                 })
             {
-                let (vertices, indices) =
-                    produce_verts(tile, (x, y), tile.rotation, self.selected, tile.model);
+                let mesh = self.mesh(tile.model);
+                let (vertices, indices) = produce_verts(tile, (x, y), tile.rotation, cursor, &mesh);
 
                 let texture = self.ground_wall_texture.clone();
 
-                self.spritebatch.push_verts(&vertices, indices, texture);
+                self.spritebatch
+                    .push_verts(&vertices, indices, texture, BlendMode::Opaque);
             }
+
+            if self.show_passability {
+                for (n, tile) in map.iter().enumerate() {
+                    let x = (n as isize % Chunk::I_WIDTH) + chunk.x * Chunk::I_WIDTH;
+                    let y = (n as isize / Chunk::I_HEIGHT) + chunk.y * Chunk::I_HEIGHT;
+
+                    let (vertices, indices) =
+                        produce_verts(tile, (x, y), TileRotation::Zero, cursor, &floor);
+                    // Floors are covered where they are, the rest at the top of their slope.
+                    // Lifted a little so the overlay doesn't fight the tile for depth.
+                    let lift = if tile.model == Model::Floor {
+                        0.01
+                    } else {
+                        1.01
+                    };
+                    let cell = tile.passability.overlay_cell() as f32;
+                    let vertices: Vec<_> = vertices
+                        .iter()
+                        .map(|vertex| Vertex {
+                            position: [
+                                vertex.position[0],
+                                vertex.position[1] + lift,
+                                vertex.position[2],
+                            ],
+                            tex_coords: [
+                                (vertex.tex_coords[0] + cell) / Self::PASSABILITY_CELLS as f32,
+                                vertex.tex_coords[1],
+                            ],
+                            normal: vertex.normal,
+                        })
+                        .collect();
+
+                    self.spritebatch.push_verts(
+                        &vertices,
+                        indices,
+                        self.passability_texture.clone(),
+                        BlendMode::Translucent,
+                    );
+                }
+            }
+        }
+
+        if let Some(world) = world {
+            let spritebatch = &mut self.spritebatch;
+            let (camera, assets) = (&state.camera, &mut state.assets);
+            world.update_frames(|sprite, animated| {
+                let frame = assets.sprite_sheet(&animated.sheet).and_then(|sheet| {
+                    let texture = assets.texture(&sprite.texture)?;
+                    Ok(animated.animation.uv(&sheet, &texture))
+                });
+                frame.unwrap_or_else(|e| {
+                    eprintln!("Failed to animate sprite: {}", e);
+                    None
+                })
+            });
+            world.for_each_sprite(|position, sprite| match assets.texture(&sprite.texture) {
+                Ok(texture) => {
+                    let drawn = spritebatch::Sprite {
+                        uv: sprite.uv,
+                        ..spritebatch::Sprite::new(position, sprite.size)
+                    };
+                    spritebatch.draw_sprite(&drawn, camera, texture, sprite.mode);
+                }
+                Err(e) => eprintln!("Failed to draw sprite: {}", e),
+            });
         }
 
-        let spritebatch_buffer = self.spritebatch.get_buffer();
+        let spritebatch_buffer = self.spritebatch.get_buffer(state.camera.eye);
         state.spritebatch_buffers = spritebatch_buffer;
+
+        stats
     }
 }
@@ -0,0 +1,190 @@
+//! A single monster, as caught or met in the wild, built from its species
+
+use super::data::GameData;
+use super::species::{BaseStats, Species};
+
+/// A lasting condition that hinders a monster until it's cured, a monster can only have one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Loses a sixteenth of its hp every turn and physical moves do half damage
+    Burn,
+    /// Loses an eighth of its hp every turn
+    Poison,
+    /// Half as fast and a quarter of the time can't move
+    Paralysis,
+    /// Can't move until it wakes up a few turns later
+    Sleep,
+}
+
+impl Status {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Burn" => Some(Status::Burn),
+            "Poison" => Some(Status::Poison),
+            "Paralysis" => Some(Status::Paralysis),
+            "Sleep" => Some(Status::Sleep),
+            _ => None,
+        }
+    }
+}
+
+/// A stat that moves can raise or lower for the rest of a battle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stat {
+    Attack,
+    Defense,
+    SpecialAttack,
+    SpecialDefense,
+    Speed,
+}
+
+impl Stat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Attack" => Some(Stat::Attack),
+            "Defense" => Some(Stat::Defense),
+            "SpecialAttack" => Some(Stat::SpecialAttack),
+            "SpecialDefense" => Some(Stat::SpecialDefense),
+            "Speed" => Some(Stat::Speed),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Stat::Attack => 0,
+            Stat::Defense => 1,
+            Stat::SpecialAttack => 2,
+            Stat::SpecialDefense => 3,
+            Stat::Speed => 4,
+        }
+    }
+}
+
+/// A monster's stats at its level
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub hp: u16,
+    pub attack: u16,
+    pub defense: u16,
+    pub special_attack: u16,
+    pub special_defense: u16,
+    pub speed: u16,
+}
+
+impl Stats {
+    /// Stats grow in a straight line from the base stats, reaching about twice them at
+    /// level 100
+    pub fn at_level(base: &BaseStats, level: u8) -> Self {
+        let grow = |base: u8| (2 * base as u32 * level as u32 / 100) as u16;
+        Self {
+            hp: grow(base.hp) + level as u16 + 10,
+            attack: grow(base.attack) + 5,
+            defense: grow(base.defense) + 5,
+            special_attack: grow(base.special_attack) + 5,
+            special_defense: grow(base.special_defense) + 5,
+            speed: grow(base.speed) + 5,
+        }
+    }
+}
+
+/// A move a monster knows and how many more times it can use it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveSlot {
+    /// Index into [`Moves::moves`](super::moves::Moves::moves)
+    pub move_index: usize,
+    pub pp: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monster {
+    /// Index into [`SpeciesRegistry::species`](super::species::SpeciesRegistry::species)
+    pub species: usize,
+    pub level: u8,
+    pub stats: Stats,
+    pub hp: u16,
+    pub moves: Vec<MoveSlot>,
+    pub status: Option<Status>,
+    /// Turns left before waking up while asleep
+    pub sleep_turns: u8,
+    /// How far each [`Stat`] has been raised or lowered in the current battle, from
+    /// -[`Monster::MAX_STAGE`] to [`Monster::MAX_STAGE`]
+    stages: [i8; 5],
+}
+
+impl Monster {
+    pub const MAX_STAGE: i8 = 6;
+
+    /// At full health and knowing the last moves its species learns by `level`
+    pub fn new(data: &GameData, species: usize, level: u8) -> Self {
+        let species_data = &data.species.species[species];
+        let stats = Stats::at_level(&species_data.base_stats, level);
+        let moves = species_data
+            .moves_at(level)
+            .into_iter()
+            .map(|move_index| MoveSlot {
+                move_index,
+                pp: data.moves.moves[move_index].pp,
+            })
+            .collect();
+
+        Self {
+            species,
+            level,
+            stats,
+            hp: stats.hp,
+            moves,
+            status: None,
+            sleep_turns: 0,
+            stages: [0; 5],
+        }
+    }
+
+    pub fn species<'a>(&self, data: &'a GameData) -> &'a Species {
+        &data.species.species[self.species]
+    }
+
+    pub fn is_fainted(&self) -> bool {
+        self.hp == 0
+    }
+
+    /// Takes up to `amount` hp, returning how much was taken
+    pub fn damage(&mut self, amount: u16) -> u16 {
+        let taken = amount.min(self.hp);
+        self.hp -= taken;
+        taken
+    }
+
+    /// `stat` after its stage. Each stage up adds half the stat again and stages down
+    /// undo that, so -1 is two thirds and -2 is half.
+    pub fn stat(&self, stat: Stat) -> u16 {
+        let value = match stat {
+            Stat::Attack => self.stats.attack,
+            Stat::Defense => self.stats.defense,
+            Stat::SpecialAttack => self.stats.special_attack,
+            Stat::SpecialDefense => self.stats.special_defense,
+            Stat::Speed => self.stats.speed,
+        } as u32;
+        let stage = self.stages[stat.index()];
+        let value = if stage >= 0 {
+            value * (2 + stage as u32) / 2
+        } else {
+            value * 2 / (2 + stage.unsigned_abs() as u32)
+        };
+        value.max(1) as u16
+    }
+
+    /// Raises or lowers `stat` by up to `stages`, returning how far it actually moved
+    pub fn change_stage(&mut self, stat: Stat, stages: i8) -> i8 {
+        let stage = &mut self.stages[stat.index()];
+        let changed = (*stage + stages).clamp(-Self::MAX_STAGE, Self::MAX_STAGE);
+        let moved = changed - *stage;
+        *stage = changed;
+        moved
+    }
+
+    /// Stages only last while the monster is out in battle
+    pub fn reset_stages(&mut self) {
+        self.stages = [0; 5];
+    }
+}
@@ -0,0 +1,384 @@
+//! The moves monsters use in battle
+
+use super::data::{quoted, Source};
+use super::monster::{Monster, Stat, Status};
+use super::types::Types;
+use nanoserde::DeRon;
+
+/// Exactly one of `status`, `stat`, `recoil` and `drain` is given
+#[derive(DeRon)]
+struct EffectConfig {
+    chance: Option<u8>,
+    status: Option<String>,
+    stat: Option<String>,
+    stages: Option<i8>,
+    on_user: Option<bool>,
+    recoil: Option<u8>,
+    drain: Option<u8>,
+}
+
+#[derive(DeRon)]
+struct MoveConfig {
+    name: String,
+    move_type: String,
+    category: String,
+    power: Option<u8>,
+    accuracy: Option<u8>,
+    pp: u8,
+    priority: Option<i8>,
+    effects: Option<Vec<EffectConfig>>,
+}
+
+#[derive(DeRon)]
+struct MovesConfig {
+    moves: Vec<MoveConfig>,
+}
+
+/// Which of the user's and target's stats a move's damage comes from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// Attack against defense
+    Physical,
+    /// Special attack against special defense
+    Special,
+    /// Does no damage
+    Status,
+}
+
+impl Category {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Physical" => Some(Category::Physical),
+            "Special" => Some(Category::Special),
+            "Status" => Some(Category::Status),
+            _ => None,
+        }
+    }
+}
+
+/// Something a move does besides its damage
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Effect {
+    /// Gives the target a status
+    Status(Status),
+    /// Raises or lowers a stat of the target, or of the user when `on_user` is set
+    Stages {
+        stat: Stat,
+        stages: i8,
+        on_user: bool,
+    },
+    /// The user takes this percent of the damage it did
+    Recoil(u8),
+    /// The user heals this percent of the damage it did
+    Drain(u8),
+}
+
+/// An [`Effect`] and the percent chance it happens when the move hits
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SecondaryEffect {
+    pub effect: Effect,
+    pub chance: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Move {
+    pub name: String,
+    /// Index into [`Types::names`]
+    pub move_type: usize,
+    pub category: Category,
+    /// 0 for status moves
+    pub power: u8,
+    /// Percent chance of hitting, always hits when `None`
+    pub accuracy: Option<u8>,
+    /// How many times it can be used in a battle
+    pub pp: u8,
+    /// Moves with a higher priority go before lower ones whatever the speed of their users
+    pub priority: i8,
+    pub effects: Vec<SecondaryEffect>,
+}
+
+/// Every move there is, species refer to them by index
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Moves {
+    pub moves: Vec<Move>,
+}
+
+impl Moves {
+    /// In [`data::DIR`](super::data::DIR)
+    pub const FILE: &'static str = "moves.ron";
+
+    pub const MAX_PRIORITY: i8 = 5;
+
+    pub fn load(path: &str, types: &Types, errors: &mut Vec<String>) -> Self {
+        match Source::read(path) {
+            Ok(source) => Self::from_source(source, types, errors),
+            Err(e) => {
+                errors.push(e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Checks the moves in `source` against `types`, every mistake is added to `errors`
+    pub fn from_source(source: Source, types: &Types, errors: &mut Vec<String>) -> Self {
+        let (source, config) = match source.parsed::<MovesConfig>(errors) {
+            Some(loaded) => loaded,
+            None => return Self::default(),
+        };
+
+        let mut moves: Vec<Move> = Vec::with_capacity(config.moves.len());
+        for entry in config.moves {
+            let name = quoted(&entry.name);
+            if moves.iter().any(|other| other.name == entry.name) {
+                errors.push(
+                    source
+                        .duplicate_error(&name, format!("move `{}` is defined twice", entry.name)),
+                );
+                continue;
+            }
+            let mut error = |message: String| {
+                errors.push(source.error(&name, format!("move `{}`: {}", entry.name, message)))
+            };
+
+            let move_type = types.find(&entry.move_type);
+            if move_type.is_none() {
+                error(format!("unknown type `{}`", entry.move_type));
+            }
+
+            let category = Category::from_name(&entry.category);
+            let power = entry.power.unwrap_or(0);
+            match category {
+                None => error(format!("unknown category `{}`", entry.category)),
+                Some(Category::Status) if power != 0 => {
+                    error("status moves can't have power".to_string())
+                }
+                Some(Category::Physical) | Some(Category::Special) if power == 0 => {
+                    error("needs power to do damage".to_string())
+                }
+                _ => {}
+            }
+
+            if let Some(accuracy) = entry.accuracy {
+                if accuracy == 0 || accuracy > 100 {
+                    error(format!("accuracy {} isn't between 1 and 100", accuracy));
+                }
+            }
+            if entry.pp == 0 {
+                error("pp can't be 0".to_string());
+            }
+            let priority = entry.priority.unwrap_or(0);
+            if !(-Self::MAX_PRIORITY..=Self::MAX_PRIORITY).contains(&priority) {
+                error(format!(
+                    "priority {} isn't between -{} and {}",
+                    priority,
+                    Self::MAX_PRIORITY,
+                    Self::MAX_PRIORITY
+                ));
+            }
+
+            let mut effects = Vec::new();
+            for effect in entry.effects.iter().flatten() {
+                match parse_effect(effect) {
+                    Ok(effect) => effects.push(effect),
+                    Err(e) => error(e),
+                }
+            }
+            if category == Some(Category::Status)
+                && effects
+                    .iter()
+                    .any(|effect| matches!(effect.effect, Effect::Recoil(_) | Effect::Drain(_)))
+            {
+                error("status moves do no damage to recoil from or drain".to_string());
+            }
+
+            // Kept even with mistakes so species that learn it don't also report it as
+            // unknown, nothing is used when there are any
+            moves.push(Move {
+                name: entry.name,
+                move_type: move_type.unwrap_or(0),
+                category: category.unwrap_or(Category::Status),
+                power,
+                accuracy: entry.accuracy,
+                pp: entry.pp,
+                priority,
+                effects,
+            });
+        }
+
+        Self { moves }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.moves.iter().position(|other| other.name == name)
+    }
+}
+
+fn parse_effect(config: &EffectConfig) -> Result<SecondaryEffect, String> {
+    let chance = config.chance.unwrap_or(100);
+    if chance == 0 || chance > 100 {
+        return Err(format!("effect chance {} isn't between 1 and 100", chance));
+    }
+
+    let effect = match (&config.status, &config.stat, config.recoil, config.drain) {
+        (Some(status), None, None, None) => match Status::from_name(status) {
+            Some(status) => Effect::Status(status),
+            None => return Err(format!("unknown status `{}`", status)),
+        },
+        (None, Some(stat), None, None) => {
+            let stat = Stat::from_name(stat).ok_or_else(|| format!("unknown stat `{}`", stat))?;
+            let stages = config.stages.unwrap_or(0);
+            if stages == 0 || !(-Monster::MAX_STAGE..=Monster::MAX_STAGE).contains(&stages) {
+                return Err(format!(
+                    "stages {} isn't between -{} and {} or is 0",
+                    stages,
+                    Monster::MAX_STAGE,
+                    Monster::MAX_STAGE
+                ));
+            }
+            Effect::Stages {
+                stat,
+                stages,
+                on_user: config.on_user.unwrap_or(false),
+            }
+        }
+        (None, None, Some(recoil), None) if recoil > 0 && recoil <= 100 => Effect::Recoil(recoil),
+        (None, None, None, Some(drain)) if drain > 0 && drain <= 100 => Effect::Drain(drain),
+        (None, None, Some(percent), None) | (None, None, None, Some(percent)) => {
+            return Err(format!(
+                "effect percent {} isn't between 1 and 100",
+                percent
+            ))
+        }
+        _ => {
+            return Err(
+                "an effect needs exactly one of `status`, `stat`, `recoil` and `drain`".to_string(),
+            )
+        }
+    };
+
+    Ok(SecondaryEffect { effect, chance })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types() -> Types {
+        let source = Source {
+            path: "types.ron".to_string(),
+            text: r#"(types: ["Normal", "Fire"], chart: [])"#.to_string(),
+        };
+        let mut errors = Vec::new();
+        let types = Types::from_source(source, &mut errors);
+        assert!(errors.is_empty(), "{:?}", errors);
+        types
+    }
+
+    /// `moves` as the list of moves, and what was wrong with them
+    fn load(moves: &str) -> (Moves, Vec<String>) {
+        let source = Source {
+            path: "moves.ron".to_string(),
+            text: format!("(\n    moves: [\n{}\n    ],\n)", moves),
+        };
+        let mut errors = Vec::new();
+        let moves = Moves::from_source(source, &types(), &mut errors);
+        (moves, errors)
+    }
+
+    #[test]
+    fn every_kind_of_effect_is_read() {
+        let (moves, errors) = load(
+            r#"        (name: "Ember", move_type: "Fire", category: "Special", power: 40, accuracy: 100, pp: 25, effects: [(status: "Burn", chance: 10)]),
+        (name: "Howl", move_type: "Normal", category: "Status", pp: 40, priority: 1, effects: [(stat: "Attack", stages: 1, on_user: true)]),
+        (name: "Slam", move_type: "Normal", category: "Physical", power: 80, pp: 20, effects: [(recoil: 25), (drain: 50)]),"#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let ember = &moves.moves[moves.find("Ember").unwrap()];
+        assert_eq!(ember.accuracy, Some(100));
+        assert_eq!(
+            ember.effects,
+            [SecondaryEffect {
+                effect: Effect::Status(Status::Burn),
+                chance: 10,
+            }]
+        );
+
+        let howl = &moves.moves[moves.find("Howl").unwrap()];
+        assert_eq!((howl.power, howl.priority), (0, 1));
+        assert_eq!(
+            howl.effects,
+            [SecondaryEffect {
+                effect: Effect::Stages {
+                    stat: Stat::Attack,
+                    stages: 1,
+                    on_user: true,
+                },
+                chance: 100,
+            }]
+        );
+
+        let slam = &moves.moves[moves.find("Slam").unwrap()];
+        assert_eq!(slam.accuracy, None);
+        let effects: Vec<_> = slam.effects.iter().map(|effect| effect.effect).collect();
+        assert_eq!(effects, [Effect::Recoil(25), Effect::Drain(50)]);
+    }
+
+    #[test]
+    fn effects_are_exactly_one_thing() {
+        let (moves, errors) = load(
+            r#"        (name: "Both", move_type: "Fire", category: "Special", power: 40, pp: 25, effects: [(status: "Burn", recoil: 10)]),
+        (name: "Neither", move_type: "Fire", category: "Special", power: 40, pp: 25, effects: [(chance: 10)]),
+        (name: "Stageless", move_type: "Fire", category: "Special", power: 40, pp: 25, effects: [(stat: "Speed")]),"#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "moves.ron:3: move `Both`: an effect needs exactly one of `status`, `stat`, `recoil` and `drain`",
+                "moves.ron:4: move `Neither`: an effect needs exactly one of `status`, `stat`, `recoil` and `drain`",
+                "moves.ron:5: move `Stageless`: stages 0 isn't between -6 and 6 or is 0",
+            ]
+        );
+        // Kept so species that learn them don't report them as unknown too
+        assert_eq!(moves.moves.len(), 3);
+        assert!(moves
+            .moves
+            .iter()
+            .all(|move_data| move_data.effects.is_empty()));
+    }
+
+    #[test]
+    fn only_moves_that_do_damage_have_power() {
+        let (_, errors) = load(
+            r#"        (name: "Glare", move_type: "Normal", category: "Status", power: 40, pp: 20),
+        (name: "Poke", move_type: "Normal", category: "Physical", pp: 20),
+        (name: "Sap", move_type: "Normal", category: "Status", pp: 20, effects: [(drain: 50)]),"#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "moves.ron:3: move `Glare`: status moves can't have power",
+                "moves.ron:4: move `Poke`: needs power to do damage",
+                "moves.ron:5: move `Sap`: status moves do no damage to recoil from or drain",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_names_and_duplicates_are_reported() {
+        let (moves, errors) = load(
+            r#"        (name: "Splash", move_type: "Water", category: "Special", power: 40, pp: 20),
+        (name: "Zap", move_type: "Normal", category: "Electric", power: 40, pp: 20),
+        (name: "Zap", move_type: "Normal", category: "Special", power: 40, pp: 20),"#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "moves.ron:3: move `Splash`: unknown type `Water`",
+                "moves.ron:4: move `Zap`: unknown category `Electric`",
+                "moves.ron:5: move `Zap` is defined twice",
+            ]
+        );
+        assert_eq!(moves.moves.len(), 2);
+    }
+}
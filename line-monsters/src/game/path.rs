@@ -0,0 +1,319 @@
+//! A* pathfinding over the map. Paths follow the same rules as walking, see
+//! [`step_target`], so anything that can follow a path step by step with a [`Walk`]
+//! never gets stuck partway. A [`Walk`] only moves up, down, left or right, which is
+//! [`Neighbours::Four`].
+//!
+//! [`Walk`]: super::walk::Walk
+
+use super::map::Map;
+use super::walk::{step_target, StepKind};
+use super::world::{Facing, GridPosition};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Which tiles around a tile a path can move to
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Neighbours {
+    Four,
+    /// Diagonal moves are only taken when both ways around the corner can be walked,
+    /// so paths never cut past a wall, a blocked tile or a change in height
+    Eight,
+}
+
+/// Costs are kept as integers so the open set can be ordered without floats
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// The tiles from `from` to `to`, both included, or `None` if there is no way there.
+/// Consecutive tiles are neighbours except after a ledge, which is jumped over to the
+/// tile beyond. `max_step` is the same as [`Walk::max_step`](super::walk::Walk::max_step).
+///
+/// Only the map is taken into account, entities standing in the way are not.
+pub fn find_path(
+    map: &Map,
+    from: GridPosition,
+    to: GridPosition,
+    max_step: u8,
+    neighbours: Neighbours,
+) -> Option<Vec<GridPosition>> {
+    map.get_tile(from.x, from.y)?;
+    map.get_tile(to.x, to.y)?;
+
+    let heuristic = |position: GridPosition| {
+        let dx = (position.x - to.x).unsigned_abs() as u32;
+        let dy = (position.y - to.y).unsigned_abs() as u32;
+        match neighbours {
+            Neighbours::Four => (dx + dy) * STRAIGHT_COST,
+            Neighbours::Eight => {
+                let (short, long) = (dx.min(dy), dx.max(dy));
+                short * DIAGONAL_COST + (long - short) * STRAIGHT_COST
+            }
+        }
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    let mut came_from = HashMap::new();
+
+    open.push(Reverse((heuristic(from), 0, from.x, from.y)));
+    costs.insert(from, 0);
+
+    while let Some(Reverse((_, cost, x, y))) = open.pop() {
+        let position = GridPosition::new(x, y);
+        if position == to {
+            let mut path = vec![to];
+            let mut current = to;
+            while let Some(previous) = came_from.get(&current) {
+                path.push(*previous);
+                current = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // A cheaper way here was already expanded
+        if costs.get(&position).map_or(false, |best| cost > *best) {
+            continue;
+        }
+
+        for (next, step_cost) in moves(map, position, max_step, neighbours) {
+            let next_cost = cost + step_cost;
+            if costs.get(&next).map_or(true, |best| next_cost < *best) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, position);
+                open.push(Reverse((
+                    next_cost + heuristic(next),
+                    next_cost,
+                    next.x,
+                    next.y,
+                )));
+            }
+        }
+    }
+
+    None
+}
+
+/// Every tile reachable in one move from `position` and what it costs to get there
+fn moves(
+    map: &Map,
+    position: GridPosition,
+    max_step: u8,
+    neighbours: Neighbours,
+) -> Vec<(GridPosition, u32)> {
+    // A jump over a ledge moves two tiles and costs as much
+    let mut moves: Vec<_> = Facing::ALL
+        .iter()
+        .filter_map(|facing| step_target(map, position, *facing, max_step))
+        .map(|(target, _)| {
+            let distance = (target.x - position.x).abs() + (target.y - position.y).abs();
+            (target, distance as u32 * STRAIGHT_COST)
+        })
+        .collect();
+
+    if neighbours == Neighbours::Eight {
+        for &vertical in &[Facing::Up, Facing::Down] {
+            for &horizontal in &[Facing::Left, Facing::Right] {
+                let diagonal = position.step(vertical).step(horizontal);
+                let around = |first: Facing, second: Facing| {
+                    let corner = walk_to(map, position, first, max_step)?;
+                    walk_to(map, corner, second, max_step)
+                };
+                if around(vertical, horizontal) == Some(diagonal)
+                    && around(horizontal, vertical) == Some(diagonal)
+                {
+                    moves.push((diagonal, DIAGONAL_COST));
+                }
+            }
+        }
+    }
+    moves
+}
+
+/// The neighbouring tile in `facing` if it can be walked onto without jumping
+fn walk_to(
+    map: &Map,
+    position: GridPosition,
+    facing: Facing,
+    max_step: u8,
+) -> Option<GridPosition> {
+    match step_target(map, position, facing, max_step)? {
+        (target, StepKind::Walk) => Some(target),
+        (_, StepKind::Jump) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::{Chunk, Passability};
+
+    fn set(
+        map: &mut Map,
+        positions: impl IntoIterator<Item = (isize, isize)>,
+        passability: Passability,
+    ) {
+        for (x, y) in positions {
+            map.get_tile_mut(x, y).unwrap().passability = passability;
+        }
+    }
+
+    fn path(map: &Map, from: (isize, isize), to: (isize, isize)) -> Option<Vec<GridPosition>> {
+        find_path(
+            map,
+            GridPosition::new(from.0, from.1),
+            GridPosition::new(to.0, to.1),
+            0,
+            Neighbours::Four,
+        )
+    }
+
+    fn diagonal_path(
+        map: &Map,
+        from: (isize, isize),
+        to: (isize, isize),
+    ) -> Option<Vec<GridPosition>> {
+        find_path(
+            map,
+            GridPosition::new(from.0, from.1),
+            GridPosition::new(to.0, to.1),
+            0,
+            Neighbours::Eight,
+        )
+    }
+
+    fn is_diagonal(from: GridPosition, to: GridPosition) -> bool {
+        (from.x - to.x).abs() == 1 && (from.y - to.y).abs() == 1
+    }
+
+    fn distance(from: GridPosition, to: GridPosition) -> isize {
+        (from.x - to.x).abs() + (from.y - to.y).abs()
+    }
+
+    #[test]
+    fn open_ground_takes_the_shortest_way() {
+        let map = Map::new();
+        let path = path(&map, (1, 2), (6, 5)).unwrap();
+        assert_eq!(path.len(), 5 + 3 + 1);
+        assert_eq!(path.first(), Some(&GridPosition::new(1, 2)));
+        assert_eq!(path.last(), Some(&GridPosition::new(6, 5)));
+        for pair in path.windows(2) {
+            assert_eq!(distance(pair[0], pair[1]), 1);
+        }
+    }
+
+    #[test]
+    fn paths_cross_chunks() {
+        let mut map = Map::new();
+        map.chunks.push(Chunk::new(1, 0));
+        let path = path(&map, (30, 0), (Chunk::I_WIDTH + 2, 0)).unwrap();
+        assert_eq!(path.len(), 5);
+    }
+
+    #[test]
+    fn blocked_tiles_and_heights_are_walked_around() {
+        // A wall down x = 2 with one gap at the bottom, partly blocked tiles and partly
+        // floor too high to step up to
+        let mut map = Map::new();
+        set(&mut map, (0..3).map(|y| (2, y)), Passability::Blocked);
+        for y in 3..5 {
+            map.get_tile_mut(2, y).unwrap().height = 2;
+        }
+
+        let path = path(&map, (0, 0), (4, 0)).unwrap();
+        assert!(path.contains(&GridPosition::new(2, 5)));
+        assert_eq!(path.len(), 2 * 5 + 4 + 1);
+    }
+
+    #[test]
+    fn ledges_are_only_jumped_their_way() {
+        let mut map = Map::new();
+        let column = (0..Chunk::I_HEIGHT).map(|y| (1, y));
+        set(&mut map, column, Passability::LedgeRight);
+
+        assert_eq!(
+            path(&map, (0, 3), (2, 3)),
+            Some(vec![GridPosition::new(0, 3), GridPosition::new(2, 3)])
+        );
+        assert_eq!(path(&map, (2, 3), (0, 3)), None);
+    }
+
+    #[test]
+    fn diagonal_paths_cut_across_open_ground() {
+        let map = Map::new();
+        let path = diagonal_path(&map, (1, 2), (6, 5)).unwrap();
+        // Three diagonal moves and two straight ones
+        assert_eq!(path.len(), 5 + 1);
+        assert_eq!(path.first(), Some(&GridPosition::new(1, 2)));
+        assert_eq!(path.last(), Some(&GridPosition::new(6, 5)));
+        let diagonals = path
+            .windows(2)
+            .filter(|pair| is_diagonal(pair[0], pair[1]))
+            .count();
+        assert_eq!(diagonals, 3);
+    }
+
+    #[test]
+    fn corners_are_never_cut() {
+        // Either tile beside the diagonal being blocked or out of reach stops it
+        let mut blocked = Map::new();
+        set(&mut blocked, vec![(3, 2)], Passability::Blocked);
+        let mut raised = Map::new();
+        raised.get_tile_mut(2, 3).unwrap().height = 2;
+        let mut ledge = Map::new();
+        set(&mut ledge, vec![(3, 2)], Passability::LedgeRight);
+
+        for map in [&blocked, &raised, &ledge] {
+            let path = diagonal_path(map, (2, 2), (3, 3)).unwrap();
+            assert_eq!(path.len(), 3);
+            assert!(!is_diagonal(path[0], path[1]));
+            assert!(!is_diagonal(path[1], path[2]));
+        }
+
+        // With both blocked the only way is the long way round
+        set(&mut raised, vec![(3, 2)], Passability::Blocked);
+        let path = diagonal_path(&raised, (2, 2), (3, 3)).unwrap();
+        assert!(path.len() > 3);
+        for pair in path.windows(2) {
+            assert!(!(pair[0] == GridPosition::new(2, 2) && pair[1] == GridPosition::new(3, 3)));
+        }
+    }
+
+    #[test]
+    fn diagonal_moves_cross_chunk_edges() {
+        let mut map = Map::new();
+        map.chunks.push(Chunk::new(1, 0));
+        let edge = Chunk::I_WIDTH - 1;
+        assert_eq!(
+            diagonal_path(&map, (edge, 1), (edge + 1, 2)),
+            Some(vec![
+                GridPosition::new(edge, 1),
+                GridPosition::new(edge + 1, 2)
+            ])
+        );
+
+        // A blocked tile on the far side of the edge stops the corner being cut
+        set(&mut map, vec![(edge + 1, 1)], Passability::Blocked);
+        let path = diagonal_path(&map, (edge, 1), (edge + 1, 2)).unwrap();
+        assert_eq!(
+            path,
+            [
+                GridPosition::new(edge, 1),
+                GridPosition::new(edge, 2),
+                GridPosition::new(edge + 1, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable_goals_have_no_path() {
+        let mut map = Map::new();
+        set(
+            &mut map,
+            vec![(4, 5), (6, 5), (5, 4), (5, 6)],
+            Passability::Blocked,
+        );
+        assert_eq!(path(&map, (0, 0), (5, 5)), None);
+        assert_eq!(diagonal_path(&map, (0, 0), (5, 5)), None);
+        assert_eq!(path(&map, (0, 0), (-1, 0)), None);
+    }
+}
@@ -0,0 +1,192 @@
+use super::ai::{self, Difficulty};
+use super::battle::{Battle, Outcome};
+use super::data::GameData;
+use super::encounter::{Encounters, TimeOfDay, WildEncounter};
+use super::map::{Map, Passability};
+use super::monster::Monster;
+use super::player::Player;
+use super::rng::Rng;
+use super::settings::PlaySettings;
+use super::world::{GridPosition, World};
+use crate::input::Input;
+use ultraviolet::Vec3;
+
+/// A wild monster the player ran into and how the battle against it went
+#[derive(Clone, Debug, PartialEq)]
+pub struct WildBattle {
+    pub encounter: WildEncounter,
+    /// `None` when neither side had won after [`Play::MAX_BATTLE_TURNS`]
+    pub outcome: Option<Outcome>,
+}
+
+impl std::fmt::Display for WildBattle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self.outcome {
+            Some(Outcome::Won(0)) => "the party won",
+            Some(Outcome::Won(_)) => "the party lost",
+            Some(Outcome::Draw) => "both sides fainted",
+            None => "neither side could win",
+        };
+        write!(
+            f,
+            "A wild {} (level {}) appeared, {}",
+            self.encounter.species, self.encounter.level, result
+        )
+    }
+}
+
+/// A play test of the map being edited. The world only lasts as long as the play test,
+/// so every one starts afresh with the player on [`Map::spawn`].
+pub struct Play {
+    pub world: World,
+    /// Where the editor camera was when play started, restored when going back to editing
+    pub editor_eye: Vec3,
+
+    rng: Rng,
+    /// Hours since midnight of the in-game clock
+    pub hour: f32,
+    /// The player's monsters, which keep their hp and status from battle to battle
+    pub party: Vec<Monster>,
+}
+
+impl Play {
+    const START_HOUR: f32 = 9.0;
+    /// An in-game hour passes every real minute
+    const HOURS_PER_SECOND: f32 = 1.0 / 60.0;
+    /// Battles that go on this long are stuck and end without a winner
+    pub const MAX_BATTLE_TURNS: u32 = 1000;
+
+    /// Seeds each play test of a session in turn from the session's `seed`, so replaying
+    /// the session rolls the same numbers
+    pub fn rngs(seed: u64) -> impl FnMut() -> Rng {
+        let mut seeds = Rng::new(seed);
+        move || Rng::new(seeds.next_u64())
+    }
+
+    /// Fails when the party in `settings` names a species that isn't in `data`
+    pub fn start(
+        map: &Map,
+        editor_eye: Vec3,
+        rng: Rng,
+        settings: &PlaySettings,
+        data: &GameData,
+    ) -> Result<Self, String> {
+        let party = settings.party(data)?;
+
+        let mut world = World::new();
+        let (x, y) = map.spawn;
+        Player::spawn(&mut world, GridPosition::new(x, y), settings);
+
+        Ok(Self {
+            world,
+            editor_eye,
+
+            rng,
+            hour: Self::START_HOUR,
+            party,
+        })
+    }
+
+    pub fn time_of_day(&self) -> TimeOfDay {
+        TimeOfDay::from_hour(self.hour)
+    }
+
+    /// Returns the battle against the wild monster the player ran into this frame, if any
+    pub fn update(
+        &mut self,
+        map: &Map,
+        encounters: &Encounters,
+        data: &GameData,
+        input: &Input,
+        dt: f32,
+    ) -> Result<Option<WildBattle>, String> {
+        self.hour = (self.hour + dt * Self::HOURS_PER_SECOND) % 24.0;
+
+        Player::control(&mut self.world, input);
+        self.world.tick(map, dt);
+
+        match self.roll_encounter(map, encounters) {
+            Some(encounter) => self.battle(data, encounter).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Rolls once for every step the player finishes, so walking on through the grass
+    /// keeps rolling
+    fn roll_encounter(&mut self, map: &Map, encounters: &Encounters) -> Option<WildEncounter> {
+        let tile = Player::finished_step(&self.world)?;
+
+        // Grass is where monsters hide, the zone painted on it picks which ones
+        if map.passability(tile.x, tile.y) != Some(Passability::EncounterGrass) {
+            return None;
+        }
+        let zone = map.get_tile(tile.x, tile.y)?.zone;
+        encounters.roll(zone, self.time_of_day(), &mut self.rng)
+    }
+
+    /// Fights `encounter` with the party until one side is beaten. Battles can't be shown
+    /// yet, so the party fights greedily on the player's behalf and the wild monster acts
+    /// at random. The party is healed after a loss so play can go on.
+    pub fn battle(
+        &mut self,
+        data: &GameData,
+        encounter: WildEncounter,
+    ) -> Result<WildBattle, String> {
+        let species = data
+            .species
+            .find(&encounter.species)
+            .ok_or_else(|| format!("unknown species `{}`", encounter.species))?;
+        let wild = Monster::new(data, species, encounter.level);
+
+        let parties = [self.party.clone(), vec![wild]];
+        let mut battle = Battle::new(data, parties, 1, Rng::new(self.rng.next_u64()))?;
+        let mut ais = [
+            Difficulty::Greedy.ai(Rng::new(self.rng.next_u64())),
+            Difficulty::Random.ai(Rng::new(self.rng.next_u64())),
+        ];
+        let outcome = ai::play_out(&mut battle, &mut ais, Self::MAX_BATTLE_TURNS)?;
+
+        self.party = battle.sides[0].party.clone();
+        for monster in self.party.iter_mut() {
+            if outcome == Some(Outcome::Won(0)) {
+                monster.reset_stages();
+            } else {
+                *monster = Monster::new(data, monster.species, monster.level);
+            }
+        }
+
+        Ok(WildBattle { encounter, outcome })
+    }
+
+    /// The point the camera follows
+    pub fn camera_target(&self) -> Option<Vec3> {
+        Player::position(&self.world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wild_battles_are_fought_to_the_end() {
+        let data = GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap();
+        let settings = PlaySettings::default();
+        let mut play =
+            Play::start(&Map::new(), Vec3::zero(), Rng::new(3), &settings, &data).unwrap();
+
+        for level in [2, 50] {
+            let encounter = WildEncounter {
+                species: "Hootwing".to_string(),
+                level,
+            };
+            let battle = play.battle(&data, encounter.clone()).unwrap();
+            assert_eq!(battle.encounter, encounter);
+            assert!(battle.outcome.is_some());
+
+            // Win or lose, the party can fight the next one
+            assert_eq!(play.party.len(), settings.party.len());
+            assert!(play.party.iter().any(|monster| monster.hp > 0));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use super::settings::PlaySettings;
+use super::walk::Walk;
+use super::world::{
+    world_position, Animated, Collision, Facing, GridPosition, Height, Sprite, World,
+};
+use crate::input::{Action, Input};
+use ultraviolet::{Vec2, Vec3};
+
+/// Marks the entity moved by the movement keys
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Player;
+
+impl Player {
+    const TEXTURE: &'static str = "textures/player.png";
+    const SHEET: &'static str = "textures/player.ron";
+
+    pub fn spawn(world: &mut World, position: GridPosition, settings: &PlaySettings) {
+        world
+            .spawn()
+            .with(Player)
+            .with(position)
+            .with(Height(0))
+            .with(Facing::Down)
+            .with(Collision)
+            .with(Walk::new(settings.walk_speed, settings.max_step))
+            .with(Sprite::new(Self::TEXTURE, Vec2::new(1.0, 1.5)))
+            .with(Animated::new(Self::SHEET))
+            .build();
+    }
+
+    /// Picks the player's next step from the held movement keys, holding a key keeps
+    /// walking in that direction
+    pub fn control(world: &mut World, input: &Input) {
+        let intent = if input.held(Action::MoveUp) {
+            Some(Facing::Up)
+        } else if input.held(Action::MoveDown) {
+            Some(Facing::Down)
+        } else if input.held(Action::MoveLeft) {
+            Some(Facing::Left)
+        } else if input.held(Action::MoveRight) {
+            Some(Facing::Right)
+        } else {
+            None
+        };
+
+        let query = world.entities.query::<(&Player, &mut Walk)>();
+        for (_, walk) in query.borrow().into_iter() {
+            walk.intent = intent;
+        }
+    }
+
+    /// The tile the player finished a step on in the last [`World::tick`], if they did
+    pub fn finished_step(world: &World) -> Option<GridPosition> {
+        let query = world.entities.query::<(&Player, &Walk)>();
+        for (_, walk) in query.borrow().into_iter() {
+            if let Some(tile) = walk.finished_step() {
+                return Some(tile);
+            }
+        }
+        None
+    }
+
+    /// Where the player is drawn, partway between tiles while stepping
+    pub fn position(world: &World) -> Option<Vec3> {
+        let query = world
+            .entities
+            .query::<(&Player, &GridPosition, &Height, &Sprite)>();
+        // Kept apart from the return value so the query's borrow ends before the query does
+        let mut drawn_at = None;
+        if let Some((_, position, height, sprite)) = query.borrow().into_iter().next() {
+            drawn_at = Some(world_position(*position, *height) + sprite.offset);
+        }
+        drawn_at
+    }
+}
@@ -0,0 +1,47 @@
+/// A small seeded random number generator (SplitMix64). The same seed always gives the
+/// same numbers on every platform, so anything rolled with it can be replayed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A seed from the clock, different on every run
+    pub fn time_seed() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number from `0` up to but not including `n`, which must not be 0
+    pub fn below(&mut self, n: u32) -> u32 {
+        (self.next_u64() % n as u64) as u32
+    }
+
+    /// A number from `min` to `max`, both included
+    pub fn range(&mut self, min: u32, max: u32) -> u32 {
+        min + (self.next_u64() % (max as u64 - min as u64 + 1)) as u32
+    }
+
+    /// A number from `0.0` up to but not including `1.0`
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// True with a probability of `p`
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.unit() < p
+    }
+}
@@ -0,0 +1,197 @@
+use super::data::GameData;
+use super::monster::Monster;
+use super::species::SpeciesRegistry;
+use super::trainer::Trainer;
+use nanoserde::DeRon;
+
+#[derive(DeRon)]
+struct PlayConfig {
+    max_step: Option<u8>,
+    walk_speed: Option<f32>,
+    party: Option<Vec<(String, u8)>>,
+}
+
+/// How the player moves in play mode, read from [`PlaySettings::CONFIG_PATH`] and then
+/// overridden from the command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaySettings {
+    /// Height difference between floors the player can walk, one more is a ledge
+    pub max_step: u8,
+    /// Tiles per second
+    pub walk_speed: f32,
+    /// Species names and levels of the monsters the player sets out with, in the order
+    /// they're sent out
+    pub party: Vec<(String, u8)>,
+}
+
+impl Default for PlaySettings {
+    fn default() -> Self {
+        Self {
+            max_step: 0,
+            walk_speed: 4.0,
+            party: vec![("Sproutle".to_string(), 5)],
+        }
+    }
+}
+
+impl PlaySettings {
+    pub const CONFIG_PATH: &'static str = "play.ron";
+
+    const MAX_STEP: u8 = 8;
+    const MIN_WALK_SPEED: f32 = 0.5;
+    const MAX_WALK_SPEED: f32 = 16.0;
+
+    /// Loads the defaults and replaces every setting given in the config file.
+    /// A missing file leaves the defaults untouched.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut settings = Self::default();
+
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(settings),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+
+        let config = PlayConfig::deserialize_ron(&src).map_err(|e| format!("{}: {:?}", path, e))?;
+
+        if let Some(max_step) = config.max_step {
+            settings
+                .set_max_step(max_step)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(walk_speed) = config.walk_speed {
+            settings
+                .set_walk_speed(walk_speed)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(party) = config.party {
+            settings
+                .set_party(party)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Replaces the settings given on the command line as `--max-step` and `--walk-speed`,
+    /// each followed by a value written the same way as in the config file
+    pub fn override_from_args(
+        &mut self,
+        arg_value: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), String> {
+        if let Some(max_step) = arg_value("--max-step") {
+            let max_step = max_step
+                .parse()
+                .map_err(|e| format!("--max-step: {}: {}", max_step, e))?;
+            self.set_max_step(max_step)
+                .map_err(|e| format!("--max-step: {}", e))?;
+        }
+        if let Some(walk_speed) = arg_value("--walk-speed") {
+            let walk_speed = walk_speed
+                .parse()
+                .map_err(|e| format!("--walk-speed: {}: {}", walk_speed, e))?;
+            self.set_walk_speed(walk_speed)
+                .map_err(|e| format!("--walk-speed: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn set_max_step(&mut self, max_step: u8) -> Result<(), String> {
+        if max_step > Self::MAX_STEP {
+            return Err(format!(
+                "max step {} is more than {}",
+                max_step,
+                Self::MAX_STEP
+            ));
+        }
+        self.max_step = max_step;
+        Ok(())
+    }
+
+    fn set_walk_speed(&mut self, walk_speed: f32) -> Result<(), String> {
+        if !(Self::MIN_WALK_SPEED..=Self::MAX_WALK_SPEED).contains(&walk_speed) {
+            return Err(format!(
+                "walk speed {} isn't between {} and {}",
+                walk_speed,
+                Self::MIN_WALK_SPEED,
+                Self::MAX_WALK_SPEED
+            ));
+        }
+        self.walk_speed = walk_speed;
+        Ok(())
+    }
+
+    fn set_party(&mut self, party: Vec<(String, u8)>) -> Result<(), String> {
+        if party.is_empty() || party.len() > Trainer::MAX_PARTY {
+            return Err(format!(
+                "the party has {} monsters, which isn't between 1 and {}",
+                party.len(),
+                Trainer::MAX_PARTY
+            ));
+        }
+        if let Some((species, level)) = party
+            .iter()
+            .find(|(_, level)| *level == 0 || *level > SpeciesRegistry::MAX_LEVEL)
+        {
+            return Err(format!(
+                "`{}` is level {}, which isn't between 1 and {}",
+                species,
+                level,
+                SpeciesRegistry::MAX_LEVEL
+            ));
+        }
+        self.party = party;
+        Ok(())
+    }
+
+    /// Fresh monsters for the party, every species in it has to be in `data`
+    pub fn party(&self, data: &GameData) -> Result<Vec<Monster>, String> {
+        self.party
+            .iter()
+            .map(|(name, level)| {
+                let species = data
+                    .species
+                    .find(name)
+                    .ok_or_else(|| format!("unknown species `{}` in the party", name))?;
+                Ok(Monster::new(data, species, *level))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_config_loads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../play.ron");
+        assert_eq!(PlaySettings::load(path).unwrap(), PlaySettings::default());
+    }
+
+    #[test]
+    fn default_party_is_in_the_game_data() {
+        let data = GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap();
+        let party = PlaySettings::default().party(&data).unwrap();
+        assert_eq!(party.len(), 1);
+        assert_eq!(party[0].level, 5);
+    }
+
+    #[test]
+    fn step_height_is_overridden_from_args() {
+        let args = |name: &str| match name {
+            "--max-step" => Some("1".to_string()),
+            _ => None,
+        };
+        let mut settings = PlaySettings::default();
+        settings.override_from_args(args).unwrap();
+        assert_eq!(settings.max_step, 1);
+
+        let too_high = |name: &str| match name {
+            "--max-step" => Some("9".to_string()),
+            _ => None,
+        };
+        assert!(settings.override_from_args(too_high).is_err());
+        assert_eq!(settings.max_step, 1);
+    }
+}
@@ -0,0 +1,142 @@
+//! Many battles fought between AIs without a player or a window, for checking how
+//! species, trainers and difficulties hold up against each other
+
+use super::ai::{self, Difficulty};
+use super::battle::{Battle, Outcome};
+use super::data::GameData;
+use super::monster::Monster;
+use super::rng::Rng;
+use super::trainer::Trainer;
+
+/// How a run of [`simulate`] went
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simulation {
+    pub battles: u32,
+    /// Battles won by each side
+    pub wins: [u32; 2],
+    /// Battles that ended in a draw or went on for [`MAX_TURNS`]
+    pub draws: u32,
+    /// Battles fought and won by each species, by index into
+    /// [`SpeciesRegistry::species`](super::species::SpeciesRegistry::species)
+    pub species: Vec<(u32, u32)>,
+}
+
+/// Battles that go on this long are stuck switching back and forth and count as draws
+pub const MAX_TURNS: u32 = 1000;
+
+/// Plays `count` single battles with each side fought by its [`Difficulty`]. Both sides
+/// are single random species at the same random level, or with `trainer` the second side
+/// is that trainer's party against random species at the same levels.
+pub fn simulate(
+    data: &GameData,
+    difficulties: [Difficulty; 2],
+    trainer: Option<&Trainer>,
+    count: u32,
+    rng: &mut Rng,
+) -> Result<Simulation, String> {
+    if data.species.species.is_empty() {
+        return Err("there are no species to battle".to_string());
+    }
+
+    let mut simulation = Simulation {
+        battles: count,
+        wins: [0; 2],
+        draws: 0,
+        species: vec![(0, 0); data.species.species.len()],
+    };
+    let species_count = simulation.species.len() as u32;
+
+    for _ in 0..count {
+        let levels: Vec<u8> = match trainer {
+            Some(trainer) => trainer.party.iter().map(|&(_, level)| level).collect(),
+            None => vec![rng.range(5, 50) as u8],
+        };
+        let mut random_party = || -> Vec<Monster> {
+            levels
+                .iter()
+                .map(|&level| Monster::new(data, rng.below(species_count) as usize, level))
+                .collect()
+        };
+        let parties = match trainer {
+            Some(trainer) => [random_party(), trainer.party(data)],
+            None => [random_party(), random_party()],
+        };
+        let species: Vec<Vec<usize>> = parties
+            .iter()
+            .map(|party| party.iter().map(|monster| monster.species).collect())
+            .collect();
+
+        let mut battle = Battle::new(data, parties, 1, Rng::new(rng.next_u64()))?;
+        let mut ais = [
+            difficulties[0].ai(Rng::new(rng.next_u64())),
+            difficulties[1].ai(Rng::new(rng.next_u64())),
+        ];
+        let outcome = ai::play_out(&mut battle, &mut ais, MAX_TURNS)?;
+
+        for &index in species.iter().flatten() {
+            simulation.species[index].0 += 1;
+        }
+        match outcome {
+            Some(Outcome::Won(side)) => {
+                simulation.wins[side] += 1;
+                for &index in &species[side] {
+                    simulation.species[index].1 += 1;
+                }
+            }
+            _ => simulation.draws += 1,
+        }
+    }
+
+    Ok(simulation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> GameData {
+        GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap()
+    }
+
+    #[test]
+    fn bulk_simulations_finish() {
+        let data = data();
+        let mut rng = Rng::new(11);
+        let pairs = [
+            [Difficulty::Random, Difficulty::Random],
+            [Difficulty::Greedy, Difficulty::Random],
+            [Difficulty::Greedy, Difficulty::Greedy],
+        ];
+        for difficulties in pairs {
+            let simulation = simulate(&data, difficulties, None, 200, &mut rng).unwrap();
+            assert_eq!(
+                simulation.wins[0] + simulation.wins[1] + simulation.draws,
+                200
+            );
+            let fought: u32 = simulation.species.iter().map(|&(fought, _)| fought).sum();
+            assert_eq!(fought, 400);
+        }
+    }
+
+    #[test]
+    fn every_trainer_can_be_simulated() {
+        let data = data();
+        let mut rng = Rng::new(12);
+        for trainer in &data.trainers.trainers {
+            let difficulties = [Difficulty::Greedy, trainer.difficulty];
+            let simulation = simulate(&data, difficulties, Some(trainer), 5, &mut rng).unwrap();
+            assert_eq!(
+                simulation.wins[0] + simulation.wins[1] + simulation.draws,
+                5
+            );
+        }
+    }
+
+    #[test]
+    fn the_same_seed_simulates_the_same() {
+        let data = data();
+        let difficulties = [Difficulty::Greedy, Difficulty::Random];
+        let run = || simulate(&data, difficulties, None, 50, &mut Rng::new(13)).unwrap();
+        assert_eq!(run(), run());
+    }
+}
@@ -0,0 +1,551 @@
+//! Monster species, one file each in the species directory of the game data so new
+//! monsters can be added without a rebuild. For example
+//!
+//! ```ron
+//! (
+//!     name: "Sproutle",
+//!     types: ["Grass"],
+//!     base_stats: (hp: 45, attack: 49, defense: 49, special_attack: 65, special_defense: 65, speed: 45),
+//!     catch_rate: 45,
+//!     sprites: (front: "textures/monsters/sproutle-front.png", back: "textures/monsters/sproutle-back.png"),
+//!     learnset: [(1, "Tackle"), (7, "Vine Whip")],
+//!     evolutions: [(into: "Bramblor", level: 16)],
+//! )
+//! ```
+
+use super::data::{quoted, Source};
+use super::encounter::TimeOfDay;
+use super::moves::Moves;
+use super::types::Types;
+use nanoserde::DeRon;
+use std::path::Path;
+
+#[derive(DeRon)]
+struct EvolutionConfig {
+    into: String,
+    level: u8,
+    time: Option<String>,
+}
+
+#[derive(DeRon)]
+struct SpeciesConfig {
+    name: String,
+    types: Vec<String>,
+    base_stats: BaseStats,
+    catch_rate: u8,
+    sprites: Sprites,
+    learnset: Vec<(u8, String)>,
+    evolutions: Option<Vec<EvolutionConfig>>,
+}
+
+/// The stats every monster of a species grows from
+#[derive(DeRon, Copy, Clone, Debug, PartialEq)]
+pub struct BaseStats {
+    pub hp: u8,
+    pub attack: u8,
+    pub defense: u8,
+    pub special_attack: u8,
+    pub special_defense: u8,
+    pub speed: u8,
+}
+
+/// Paths in the assets directory
+#[derive(DeRon, Clone, Debug, PartialEq)]
+pub struct Sprites {
+    /// Seen across the battlefield
+    pub front: String,
+    /// Seen from behind when it's the player's
+    pub back: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Evolution {
+    /// Index into [`SpeciesRegistry::species`]
+    pub into: usize,
+    /// Evolves on reaching this level
+    pub level: u8,
+    /// Only evolves at this time of day, any time when `None`
+    pub time: Option<TimeOfDay>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Species {
+    pub name: String,
+    /// One or two indices into [`Types::names`]
+    pub types: Vec<usize>,
+    pub base_stats: BaseStats,
+    /// From 1 to 255, higher is easier to catch
+    pub catch_rate: u8,
+    pub sprites: Sprites,
+    /// The level each move is learned at with its index into [`Moves::moves`],
+    /// sorted by level
+    pub learnset: Vec<(u8, usize)>,
+    pub evolutions: Vec<Evolution>,
+}
+
+impl Species {
+    /// A monster knows up to this many moves
+    pub const MAX_MOVES: usize = 4;
+
+    /// The moves a monster of this species caught at `level` knows, the last ones it
+    /// would have learned on the way there
+    pub fn moves_at(&self, level: u8) -> Vec<usize> {
+        let learned: Vec<_> = self
+            .learnset
+            .iter()
+            .filter(|(learned_at, _)| *learned_at <= level)
+            .map(|(_, move_index)| *move_index)
+            .collect();
+        let skip = learned.len().saturating_sub(Self::MAX_MOVES);
+        learned[skip..].to_vec()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct SpeciesRegistry {
+    pub species: Vec<Species>,
+}
+
+impl SpeciesRegistry {
+    /// In [`data::DIR`](super::data::DIR), every `.ron` file in it is a species
+    pub const DIR: &'static str = "species";
+
+    pub const MAX_LEVEL: u8 = 100;
+
+    /// Loads the species in `dir` in file name order, adding every mistake to `errors`.
+    /// Sprites are looked for in `assets`.
+    pub fn load(
+        dir: &str,
+        assets: &Path,
+        types: &Types,
+        moves: &Moves,
+        errors: &mut Vec<String>,
+    ) -> Self {
+        let mut paths: Vec<_> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .map_or(false, |extension| extension == "ron")
+                })
+                .collect(),
+            Err(e) => {
+                errors.push(format!("{}: {}", dir, e));
+                return Self::default();
+            }
+        };
+        paths.sort();
+
+        let mut sources = Vec::with_capacity(paths.len());
+        for path in paths {
+            match Source::read(&path.to_string_lossy()) {
+                Ok(source) => sources.push(source),
+                Err(e) => errors.push(e),
+            }
+        }
+        Self::from_sources(sources, assets, types, moves, errors)
+    }
+
+    /// Checks the species already read from their files, one per source
+    pub fn from_sources(
+        sources: Vec<Source>,
+        assets: &Path,
+        types: &Types,
+        moves: &Moves,
+        errors: &mut Vec<String>,
+    ) -> Self {
+        let loaded: Vec<(Source, SpeciesConfig)> = sources
+            .into_iter()
+            .filter_map(|source| source.parsed(errors))
+            .collect();
+
+        // Evolutions can point at species in any file, so every name is known before
+        // any species is checked
+        let names: Vec<&str> = loaded
+            .iter()
+            .map(|(_, config)| config.name.as_str())
+            .collect();
+
+        let mut species = Vec::with_capacity(loaded.len());
+        for (n, (source, config)) in loaded.iter().enumerate() {
+            if config.name.is_empty() {
+                errors.push(source.error("name:", "species has no name"));
+            } else if let Some(first) = names[..n].iter().position(|name| *name == config.name) {
+                errors.push(source.error(
+                    &quoted(&config.name),
+                    format!(
+                        "species `{}` is already defined in {}",
+                        config.name, loaded[first].0.path
+                    ),
+                ));
+            }
+
+            let species_types = check_types(source, config, types, errors);
+
+            let stats = &config.base_stats;
+            for (name, value) in &[
+                ("hp", stats.hp),
+                ("attack", stats.attack),
+                ("defense", stats.defense),
+                ("special_attack", stats.special_attack),
+                ("special_defense", stats.special_defense),
+                ("speed", stats.speed),
+            ] {
+                if *value == 0 {
+                    errors.push(source.error(
+                        &format!("{}:", name),
+                        format!("{}: base {} can't be 0", config.name, name),
+                    ));
+                }
+            }
+
+            if config.catch_rate == 0 {
+                errors.push(source.error(
+                    "catch_rate:",
+                    format!("{}: catch rate can't be 0", config.name),
+                ));
+            }
+
+            check_sprites(source, config, assets, errors);
+            let learnset = check_learnset(source, config, moves, errors);
+            let evolutions = check_evolutions(source, config, &names, errors);
+
+            species.push(Species {
+                name: config.name.clone(),
+                types: species_types,
+                base_stats: config.base_stats,
+                catch_rate: config.catch_rate,
+                sprites: config.sprites.clone(),
+                learnset,
+                evolutions,
+            });
+        }
+
+        Self { species }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.species.iter().position(|species| species.name == name)
+    }
+}
+
+fn check_types(
+    source: &Source,
+    config: &SpeciesConfig,
+    types: &Types,
+    errors: &mut Vec<String>,
+) -> Vec<usize> {
+    if config.types.is_empty() || config.types.len() > 2 {
+        errors.push(source.error("types:", format!("{}: needs one or two types", config.name)));
+    }
+
+    let mut species_types = Vec::with_capacity(config.types.len());
+    for name in &config.types {
+        match types.find(name) {
+            Some(index) if species_types.contains(&index) => errors.push(source.error(
+                &quoted(name),
+                format!("{}: type `{}` is given twice", config.name, name),
+            )),
+            Some(index) => species_types.push(index),
+            None => errors.push(source.error(
+                &quoted(name),
+                format!("{}: unknown type `{}`", config.name, name),
+            )),
+        }
+    }
+    species_types
+}
+
+fn check_sprites(source: &Source, config: &SpeciesConfig, assets: &Path, errors: &mut Vec<String>) {
+    for path in &[&config.sprites.front, &config.sprites.back] {
+        if !assets.join(path).is_file() {
+            errors.push(source.error(
+                &quoted(path),
+                format!(
+                    "{}: there's no sprite `{}` in {}",
+                    config.name,
+                    path,
+                    assets.display()
+                ),
+            ));
+        }
+    }
+}
+
+fn check_learnset(
+    source: &Source,
+    config: &SpeciesConfig,
+    moves: &Moves,
+    errors: &mut Vec<String>,
+) -> Vec<(u8, usize)> {
+    let mut learnset = Vec::with_capacity(config.learnset.len());
+    for (level, name) in &config.learnset {
+        if *level == 0 || *level > SpeciesRegistry::MAX_LEVEL {
+            errors.push(source.error(
+                &quoted(name),
+                format!(
+                    "{}: `{}` is learned at level {}, which isn't between 1 and {}",
+                    config.name,
+                    name,
+                    level,
+                    SpeciesRegistry::MAX_LEVEL
+                ),
+            ));
+        }
+        match moves.find(name) {
+            Some(index) => learnset.push((*level, index)),
+            None => errors.push(source.error(
+                &quoted(name),
+                format!("{}: unknown move `{}`", config.name, name),
+            )),
+        }
+    }
+
+    if !learnset.iter().any(|(level, _)| *level == 1) {
+        errors.push(source.error(
+            "learnset:",
+            format!("{}: learns no moves at level 1", config.name),
+        ));
+    }
+
+    learnset.sort_by_key(|(level, _)| *level);
+    learnset
+}
+
+fn check_evolutions(
+    source: &Source,
+    config: &SpeciesConfig,
+    names: &[&str],
+    errors: &mut Vec<String>,
+) -> Vec<Evolution> {
+    let mut evolutions = Vec::new();
+    for evolution in config.evolutions.iter().flatten() {
+        let needle = format!("into: {}", quoted(&evolution.into));
+
+        if evolution.into == config.name {
+            errors.push(source.error(
+                &needle,
+                format!("{}: can't evolve into itself", config.name),
+            ));
+            continue;
+        }
+        if evolution.level == 0 || evolution.level > SpeciesRegistry::MAX_LEVEL {
+            errors.push(source.error(
+                &needle,
+                format!(
+                    "{}: evolves at level {}, which isn't between 1 and {}",
+                    config.name,
+                    evolution.level,
+                    SpeciesRegistry::MAX_LEVEL
+                ),
+            ));
+        }
+        let time = match &evolution.time {
+            Some(name) => match TimeOfDay::from_name(name) {
+                Some(time) => Some(time),
+                None => {
+                    errors.push(source.error(
+                        &needle,
+                        format!("{}: unknown time of day `{}`", config.name, name),
+                    ));
+                    None
+                }
+            },
+            None => None,
+        };
+
+        match names.iter().position(|name| *name == evolution.into) {
+            Some(into) => evolutions.push(Evolution {
+                into,
+                level: evolution.level,
+                time,
+            }),
+            None => errors.push(source.error(
+                &needle,
+                format!(
+                    "{}: evolves into unknown species `{}`",
+                    config.name, evolution.into
+                ),
+            )),
+        }
+    }
+    evolutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types() -> Types {
+        let source = Source {
+            path: "types.ron".to_string(),
+            text: r#"(types: ["Normal", "Grass"], chart: [])"#.to_string(),
+        };
+        let mut errors = Vec::new();
+        let types = Types::from_source(source, &mut errors);
+        assert!(errors.is_empty(), "{:?}", errors);
+        types
+    }
+
+    fn moves(types: &Types) -> Moves {
+        let source = Source {
+            path: "moves.ron".to_string(),
+            text: r#"(moves: [
+                (name: "Tackle", move_type: "Normal", category: "Physical", power: 40, accuracy: 100, pp: 35),
+                (name: "Vine Whip", move_type: "Grass", category: "Physical", power: 45, accuracy: 100, pp: 25),
+            ])"#
+            .to_string(),
+        };
+        let mut errors = Vec::new();
+        let moves = Moves::from_source(source, types, &mut errors);
+        assert!(errors.is_empty(), "{:?}", errors);
+        moves
+    }
+
+    /// A species file at `path` with Sproutle's sprites, one field per line
+    fn species(path: &str, name: &str, types: &str, learnset: &str, evolutions: &str) -> Source {
+        Source {
+            path: path.to_string(),
+            text: format!(
+                r#"(
+    name: "{}",
+    types: [{}],
+    base_stats: (hp: 45, attack: 49, defense: 49, special_attack: 65, special_defense: 65, speed: 45),
+    catch_rate: 45,
+    sprites: (front: "textures/monsters/sproutle-front.png", back: "textures/monsters/sproutle-back.png"),
+    learnset: [{}],
+    evolutions: [{}],
+)"#,
+                name, types, learnset, evolutions
+            ),
+        }
+    }
+
+    /// The species in `sources` with the shipped assets, and what was wrong with them
+    fn load(sources: Vec<Source>) -> (SpeciesRegistry, Vec<String>) {
+        let assets = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../assets"));
+        let types = types();
+        let moves = moves(&types);
+        let mut errors = Vec::new();
+        let species = SpeciesRegistry::from_sources(sources, assets, &types, &moves, &mut errors);
+        (species, errors)
+    }
+
+    #[test]
+    fn evolutions_point_at_species_in_later_files() {
+        let (registry, errors) = load(vec![
+            species(
+                "sproutle.ron",
+                "Sproutle",
+                r#""Grass""#,
+                r#"(7, "Vine Whip"), (1, "Tackle")"#,
+                r#"(into: "Bramblor", level: 16, time: "Night")"#,
+            ),
+            species(
+                "bramblor.ron",
+                "Bramblor",
+                r#""Grass", "Normal""#,
+                r#"(1, "Vine Whip")"#,
+                "",
+            ),
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let sproutle = &registry.species[registry.find("Sproutle").unwrap()];
+        assert_eq!(
+            sproutle.evolutions,
+            [Evolution {
+                into: registry.find("Bramblor").unwrap(),
+                level: 16,
+                time: Some(TimeOfDay::Night),
+            }]
+        );
+        // Sorted by level whatever order they're written in
+        let levels: Vec<u8> = sproutle.learnset.iter().map(|(level, _)| *level).collect();
+        assert_eq!(levels, [1, 7]);
+    }
+
+    #[test]
+    fn unknown_types_and_moves_are_reported_on_their_line() {
+        let (_, errors) = load(vec![species(
+            "sproutle.ron",
+            "Sproutle",
+            r#""Grass", "Fire""#,
+            r#"(5, "Vine Whip"), (7, "Leaf Storm")"#,
+            "",
+        )]);
+        assert_eq!(
+            errors,
+            [
+                "sproutle.ron:3: Sproutle: unknown type `Fire`",
+                "sproutle.ron:7: Sproutle: unknown move `Leaf Storm`",
+                "sproutle.ron:7: Sproutle: learns no moves at level 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn names_can_only_be_used_once() {
+        let (_, errors) = load(vec![
+            species("a.ron", "Sproutle", r#""Grass""#, r#"(1, "Tackle")"#, ""),
+            species("b.ron", "Sproutle", r#""Grass""#, r#"(1, "Tackle")"#, ""),
+        ]);
+        assert_eq!(
+            errors,
+            ["b.ron:2: species `Sproutle` is already defined in a.ron"]
+        );
+    }
+
+    #[test]
+    fn evolutions_need_another_known_species() {
+        let (registry, errors) = load(vec![
+            species(
+                "sproutle.ron",
+                "Sproutle",
+                r#""Grass""#,
+                r#"(1, "Tackle")"#,
+                r#"(into: "Sproutle", level: 16)"#,
+            ),
+            species(
+                "bramblor.ron",
+                "Bramblor",
+                r#""Grass""#,
+                r#"(1, "Tackle")"#,
+                r#"(into: "Thornking", level: 36)"#,
+            ),
+        ]);
+        assert_eq!(
+            errors,
+            [
+                "sproutle.ron:8: Sproutle: can't evolve into itself",
+                "bramblor.ron:8: Bramblor: evolves into unknown species `Thornking`",
+            ]
+        );
+        assert!(registry
+            .species
+            .iter()
+            .all(|species| species.evolutions.is_empty()));
+    }
+
+    #[test]
+    fn sprites_have_to_exist_in_the_assets() {
+        let mut source = species(
+            "sproutle.ron",
+            "Sproutle",
+            r#""Grass""#,
+            r#"(1, "Tackle")"#,
+            "",
+        );
+        source.text = source.text.replace("sproutle-back", "sproutle-behind");
+        let (_, errors) = load(vec![source]);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].starts_with(
+                "sproutle.ron:6: Sproutle: there's no sprite `textures/monsters/sproutle-behind.png` in "
+            ),
+            "{}",
+            errors[0]
+        );
+    }
+}
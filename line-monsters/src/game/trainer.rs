@@ -0,0 +1,115 @@
+//! Trainers the player can battle, with their parties and how cleverly they fight
+
+use super::ai::Difficulty;
+use super::data::{quoted, GameData, Source};
+use super::monster::Monster;
+use super::species::SpeciesRegistry;
+use nanoserde::DeRon;
+
+#[derive(DeRon)]
+struct TrainerConfig {
+    name: String,
+    difficulty: String,
+    party: Vec<(String, u8)>,
+}
+
+#[derive(DeRon)]
+struct TrainersConfig {
+    trainers: Vec<TrainerConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trainer {
+    pub name: String,
+    pub difficulty: Difficulty,
+    /// Indices into [`SpeciesRegistry::species`] with their levels, in the order
+    /// they're sent out
+    pub party: Vec<(usize, u8)>,
+}
+
+impl Trainer {
+    /// A party can't be bigger than this
+    pub const MAX_PARTY: usize = 6;
+
+    /// Fresh monsters for a battle against this trainer
+    pub fn party(&self, data: &GameData) -> Vec<Monster> {
+        self.party
+            .iter()
+            .map(|&(species, level)| Monster::new(data, species, level))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Trainers {
+    pub trainers: Vec<Trainer>,
+}
+
+impl Trainers {
+    /// In [`data::DIR`](super::data::DIR)
+    pub const FILE: &'static str = "trainers.ron";
+
+    pub fn load(path: &str, species: &SpeciesRegistry, errors: &mut Vec<String>) -> Self {
+        let (source, config) = match Source::load::<TrainersConfig>(path, errors) {
+            Some(loaded) => loaded,
+            None => return Self::default(),
+        };
+
+        let mut trainers: Vec<Trainer> = Vec::with_capacity(config.trainers.len());
+        for entry in config.trainers {
+            let name = quoted(&entry.name);
+            if trainers.iter().any(|other| other.name == entry.name) {
+                errors.push(
+                    source.duplicate_error(
+                        &name,
+                        format!("trainer `{}` is defined twice", entry.name),
+                    ),
+                );
+                continue;
+            }
+            let mut error = |message: String| {
+                errors.push(source.error(&name, format!("trainer `{}`: {}", entry.name, message)))
+            };
+
+            let difficulty = Difficulty::from_name(&entry.difficulty);
+            if difficulty.is_none() {
+                error(format!("unknown difficulty `{}`", entry.difficulty));
+            }
+
+            if entry.party.is_empty() || entry.party.len() > Trainer::MAX_PARTY {
+                error(format!(
+                    "has {} monsters, which isn't between 1 and {}",
+                    entry.party.len(),
+                    Trainer::MAX_PARTY
+                ));
+            }
+            let mut party = Vec::with_capacity(entry.party.len());
+            for (species_name, level) in &entry.party {
+                if *level == 0 || *level > SpeciesRegistry::MAX_LEVEL {
+                    error(format!(
+                        "`{}` is level {}, which isn't between 1 and {}",
+                        species_name,
+                        level,
+                        SpeciesRegistry::MAX_LEVEL
+                    ));
+                }
+                match species.find(species_name) {
+                    Some(index) => party.push((index, *level)),
+                    None => error(format!("unknown species `{}`", species_name)),
+                }
+            }
+
+            trainers.push(Trainer {
+                name: entry.name,
+                difficulty: difficulty.unwrap_or(Difficulty::Random),
+                party,
+            });
+        }
+
+        Self { trainers }
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.trainers.iter().position(|other| other.name == name)
+    }
+}
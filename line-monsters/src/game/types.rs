@@ -0,0 +1,231 @@
+//! The elemental types of monsters and moves, such as Fire or Water, and how well each
+//! type of move works against each type of monster
+
+use super::data::{quoted, Source};
+use nanoserde::DeRon;
+
+#[derive(DeRon)]
+struct TypesConfig {
+    types: Vec<String>,
+    chart: Vec<(String, String, f32)>,
+}
+
+/// Every type there is, species and moves refer to them by index
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Types {
+    pub names: Vec<String>,
+    /// Damage multipliers indexed by the attacking type times the number of types plus
+    /// the defending type
+    chart: Vec<f32>,
+}
+
+impl Types {
+    /// In [`data::DIR`](super::data::DIR)
+    pub const FILE: &'static str = "types.ron";
+
+    /// The multipliers the chart can use, no damage, not very effective, normal and
+    /// super effective
+    const MULTIPLIERS: &'static [f32] = &[0.0, 0.5, 1.0, 2.0];
+
+    pub fn load(path: &str, errors: &mut Vec<String>) -> Self {
+        match Source::read(path) {
+            Ok(source) => Self::from_source(source, errors),
+            Err(e) => {
+                errors.push(e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Checks the types and chart in `source`, every mistake is added to `errors`
+    pub fn from_source(source: Source, errors: &mut Vec<String>) -> Self {
+        let (source, config) = match source.parsed::<TypesConfig>(errors) {
+            Some(loaded) => loaded,
+            None => return Self::default(),
+        };
+
+        let mut names: Vec<String> = Vec::with_capacity(config.types.len());
+        for name in config.types {
+            if name.is_empty() {
+                errors.push(source.error("\"\"", "a type has no name"));
+            } else if names.contains(&name) {
+                errors.push(
+                    source.duplicate_error(
+                        &quoted(&name),
+                        format!("type `{}` is defined twice", name),
+                    ),
+                );
+            } else {
+                names.push(name);
+            }
+        }
+
+        let mut types = Self {
+            chart: vec![1.0; names.len() * names.len()],
+            names,
+        };
+        let mut given = vec![false; types.chart.len()];
+        for (attacking, defending, multiplier) in &config.chart {
+            // With the bracket so it isn't found in the list of types
+            let pair = format!("({}, {}", quoted(attacking), quoted(defending));
+            let (attacking_index, defending_index) =
+                match (types.find(attacking), types.find(defending)) {
+                    (Some(attacking), Some(defending)) => (attacking, defending),
+                    (None, _) => {
+                        errors.push(
+                            source.error(&pair, format!("chart: unknown type `{}`", attacking)),
+                        );
+                        continue;
+                    }
+                    (_, None) => {
+                        errors.push(
+                            source.error(&pair, format!("chart: unknown type `{}`", defending)),
+                        );
+                        continue;
+                    }
+                };
+            if !Self::MULTIPLIERS.contains(multiplier) {
+                errors.push(source.error(
+                    &pair,
+                    format!(
+                        "chart: {} against {} is {}, which isn't one of {:?}",
+                        attacking,
+                        defending,
+                        multiplier,
+                        Self::MULTIPLIERS
+                    ),
+                ));
+                continue;
+            }
+
+            let index = types.chart_index(attacking_index, defending_index);
+            if given[index] {
+                errors.push(source.duplicate_error(
+                    &pair,
+                    format!("chart: {} against {} is given twice", attacking, defending),
+                ));
+            }
+            given[index] = true;
+            types.chart[index] = *multiplier;
+        }
+
+        types
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|other| other == name)
+    }
+
+    fn chart_index(&self, attacking: usize, defending: usize) -> usize {
+        attacking * self.names.len() + defending
+    }
+
+    /// How much damage a move of the `attacking` type does to a monster of the `defending`
+    /// type, 1 when the chart doesn't say
+    pub fn effectiveness(&self, attacking: usize, defending: usize) -> f32 {
+        self.chart
+            .get(self.chart_index(attacking, defending))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// The multipliers against each of a monster's types multiplied together
+    pub fn effectiveness_against(&self, attacking: usize, defending: &[usize]) -> f32 {
+        defending
+            .iter()
+            .map(|&defending| self.effectiveness(attacking, defending))
+            .product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Five types with `chart` as the chart, and what was wrong with it
+    fn load(chart: &str) -> (Types, Vec<String>) {
+        let source = Source {
+            path: "types.ron".to_string(),
+            text: format!(
+                "(\n    types: [\"Normal\", \"Fire\", \"Water\", \"Flying\", \"Ground\"],\n    chart: [\n{}\n    ],\n)",
+                chart
+            ),
+        };
+        let mut errors = Vec::new();
+        let types = Types::from_source(source, &mut errors);
+        (types, errors)
+    }
+
+    #[test]
+    fn multipliers_are_looked_up_and_default_to_1() {
+        let (types, errors) = load(
+            r#"        ("Fire", "Water", 0.5),
+        ("Water", "Fire", 2.0),
+        ("Ground", "Flying", 0.0),"#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        let [normal, fire, water, flying, ground] =
+            ["Normal", "Fire", "Water", "Flying", "Ground"].map(|name| types.find(name).unwrap());
+
+        assert_eq!(types.effectiveness(fire, water), 0.5);
+        assert_eq!(types.effectiveness(water, fire), 2.0);
+        assert_eq!(types.effectiveness(ground, flying), 0.0);
+        // Only the way round that's listed
+        assert_eq!(types.effectiveness(flying, ground), 1.0);
+        assert_eq!(types.effectiveness(normal, fire), 1.0);
+    }
+
+    #[test]
+    fn both_types_of_a_monster_count() {
+        let (types, errors) = load(
+            r#"        ("Water", "Fire", 2.0),
+        ("Water", "Ground", 2.0),
+        ("Fire", "Water", 0.5),
+        ("Ground", "Flying", 0.0),"#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        let [normal, fire, water, flying, ground] =
+            ["Normal", "Fire", "Water", "Flying", "Ground"].map(|name| types.find(name).unwrap());
+
+        assert_eq!(types.effectiveness_against(water, &[fire, ground]), 4.0);
+        assert_eq!(types.effectiveness_against(fire, &[water, normal]), 0.5);
+        assert_eq!(types.effectiveness_against(ground, &[fire, flying]), 0.0);
+        assert_eq!(types.effectiveness_against(water, &[fire]), 2.0);
+    }
+
+    #[test]
+    fn mistakes_in_the_chart_are_reported_on_their_line() {
+        let (types, errors) = load(
+            r#"        ("Fire", "Ice", 2.0),
+        ("Ice", "Fire", 0.5),
+        ("Fire", "Water", 0.25),
+        ("Water", "Fire", 2.0),
+        ("Water", "Fire", 0.5),"#,
+        );
+        assert_eq!(
+            errors,
+            [
+                "types.ron:4: chart: unknown type `Ice`",
+                "types.ron:5: chart: unknown type `Ice`",
+                "types.ron:6: chart: Fire against Water is 0.25, which isn't one of [0.0, 0.5, 1.0, 2.0]",
+                "types.ron:8: chart: Water against Fire is given twice",
+            ]
+        );
+        // What could be read is still used
+        let fire = types.find("Fire").unwrap();
+        let water = types.find("Water").unwrap();
+        assert_eq!(types.effectiveness(fire, water), 1.0);
+    }
+
+    #[test]
+    fn types_are_only_defined_once() {
+        let source = Source {
+            path: "types.ron".to_string(),
+            text: "(\n    types: [\"Fire\",\n        \"Fire\"],\n    chart: [],\n)".to_string(),
+        };
+        let mut errors = Vec::new();
+        let types = Types::from_source(source, &mut errors);
+        assert_eq!(errors, ["types.ron:3: type `Fire` is defined twice"]);
+        assert_eq!(types.names, ["Fire"]);
+    }
+}
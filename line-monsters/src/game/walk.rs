@@ -0,0 +1,260 @@
+//! Tile to tile movement over the terrain the editor builds. Floors can be walked between
+//! when their heights are close enough, walls block the way unless they're a ledge.
+
+use super::map::{Map, Model, TileRotation};
+use super::world::{world_position, Facing, GridPosition, Height};
+use ultraviolet::Vec3;
+
+/// How an entity gets onto the tile a step ends on
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StepKind {
+    Walk,
+    /// Down a ledge, there's no way back up the same way
+    Jump,
+}
+
+impl TileRotation {
+    /// The direction a wall with this rotation slopes down towards
+    fn downhill(self) -> Facing {
+        match self {
+            TileRotation::Zero => Facing::Down,
+            TileRotation::Quarter => Facing::Right,
+            TileRotation::Half => Facing::Up,
+            TileRotation::ThreeQuarters => Facing::Left,
+        }
+    }
+}
+
+/// Where a step in `facing` from `from` ends and how it gets there, `None` if the way
+/// is blocked.
+///
+/// Floors whose heights differ by at most `max_step` can be walked between, and a drop
+/// of one more than that is jumped down. A `Wall` that slopes down in the direction of
+/// travel from a floor one higher is a ledge, which is jumped over to land on the floor
+/// at its foot. Every other wall and corner, and tiles off the map, block the way.
+///
+/// A tile's `passability` can block it further. Ledge tiles are jumped over in their
+/// direction onto a floor no higher than the start, and surfable water can't be walked.
+pub fn step_target(
+    map: &Map,
+    from: GridPosition,
+    facing: Facing,
+    max_step: u8,
+) -> Option<(GridPosition, StepKind)> {
+    let current = map.get_tile(from.x, from.y)?;
+    let next = from.step(facing);
+    let tile = map.get_tile(next.x, next.y)?;
+
+    if let Some(ledge) = tile.passability.ledge() {
+        if ledge != facing {
+            return None;
+        }
+        let landing = next.step(facing);
+        let landing_tile = map.get_tile(landing.x, landing.y)?;
+        return if landing_tile.model == Model::Floor
+            && landing_tile.passability.is_walkable()
+            && landing_tile.height <= current.height
+        {
+            Some((landing, StepKind::Jump))
+        } else {
+            None
+        };
+    }
+    if !tile.passability.is_walkable() {
+        return None;
+    }
+
+    match tile.model {
+        Model::Floor => {
+            let rise = tile.height as i16 - current.height as i16;
+            if rise.abs() <= max_step as i16 {
+                Some((next, StepKind::Walk))
+            } else if rise == -(max_step as i16 + 1) {
+                Some((next, StepKind::Jump))
+            } else {
+                None
+            }
+        }
+        Model::Wall
+            if tile.rotation.downhill() == facing
+                && current.height.checked_sub(1) == Some(tile.height) =>
+        {
+            let landing = next.step(facing);
+            let landing_tile = map.get_tile(landing.x, landing.y)?;
+            if landing_tile.model == Model::Floor
+                && landing_tile.passability.is_walkable()
+                && landing_tile.height == tile.height
+            {
+                Some((landing, StepKind::Jump))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The top of the tile at `position`
+fn surface(map: &Map, position: GridPosition) -> Vec3 {
+    let height = map
+        .get_tile(position.x, position.y)
+        .map_or(0, |tile| tile.height);
+    world_position(position, Height(height))
+}
+
+/// A step in progress, the entity's [`GridPosition`] is already the tile it ends on
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Step {
+    /// Where the step started relative to where it ends
+    from: Vec3,
+    elapsed: f32,
+    duration: f32,
+    kind: StepKind,
+}
+
+impl Step {
+    /// How high above the straight line between the tiles a jump peaks
+    const JUMP_HEIGHT: f32 = 0.5;
+
+    /// Where the entity is drawn relative to the tile the step ends on
+    fn offset(&self) -> Vec3 {
+        let t = (self.elapsed / self.duration).min(1.0);
+        let arc = match self.kind {
+            StepKind::Walk => 0.0,
+            StepKind::Jump => (t * std::f32::consts::PI).sin() * Self::JUMP_HEIGHT,
+        };
+        self.from * (1.0 - t) + Vec3::unit_y() * arc
+    }
+}
+
+/// Moves an entity a tile at a time in the direction it's told to
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Walk {
+    /// Tiles per second
+    pub speed: f32,
+    /// The largest height difference between floors that can be walked, see [`step_target`]
+    pub max_step: u8,
+    /// The direction to step in once the current step is done, set by whatever controls
+    /// the entity and cleared when the step starts
+    pub intent: Option<Facing>,
+    step: Option<Step>,
+    /// The tile the last [`Walk::update`] finished a step on
+    finished: Option<GridPosition>,
+}
+
+impl Walk {
+    pub fn new(speed: f32, max_step: u8) -> Self {
+        Self {
+            speed,
+            max_step,
+            intent: None,
+            step: None,
+            finished: None,
+        }
+    }
+
+    pub fn is_stepping(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// The tile the last [`Walk::update`] finished a step on, `None` if it didn't. Walking
+    /// on while a movement key is held finishes a step every time, even though the next
+    /// one starts straight away.
+    pub fn finished_step(&self) -> Option<GridPosition> {
+        self.finished
+    }
+
+    /// Where the entity should be drawn relative to its tile
+    pub fn offset(&self) -> Vec3 {
+        self.step.map_or(Vec3::zero(), |step| step.offset())
+    }
+
+    /// Carries on with the current step, or starts the next one when there isn't one.
+    /// A blocked step only turns the entity to face it. Steps onto a tile in `occupied`
+    /// are blocked, and the tile a new step ends on is added to it.
+    ///
+    /// Returns the tile a step finished on, see [`Walk::finished_step`].
+    pub fn update(
+        &mut self,
+        map: &Map,
+        position: &mut GridPosition,
+        facing: &mut Facing,
+        occupied: &mut Vec<GridPosition>,
+        dt: f32,
+    ) -> Option<GridPosition> {
+        self.finished = None;
+
+        // Time left over from a finished step goes towards the next, so walking in a
+        // straight line doesn't stutter
+        let mut leftover = 0.0;
+        if let Some(step) = &mut self.step {
+            step.elapsed += dt;
+            if step.elapsed < step.duration {
+                return None;
+            }
+            leftover = step.elapsed - step.duration;
+            self.step = None;
+            self.finished = Some(*position);
+        }
+
+        if let Some(intent) = self.intent.take() {
+            *facing = intent;
+            if let Some((target, kind)) = step_target(map, *position, intent, self.max_step)
+                .filter(|(target, _)| !occupied.contains(target))
+            {
+                let distance =
+                    ((target.x - position.x).abs() + (target.y - position.y).abs()) as f32;
+                self.step = Some(Step {
+                    from: surface(map, *position) - surface(map, target),
+                    elapsed: leftover,
+                    duration: distance / self.speed,
+                    kind,
+                });
+                *position = target;
+                occupied.push(target);
+            }
+        }
+
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_step_of_a_held_walk_finishes() {
+        let map = Map::new();
+        let mut walk = Walk::new(4.0, 0);
+        let mut position = GridPosition::new(0, 0);
+        let mut facing = Facing::Up;
+
+        // A frame is a fifth of a step, with the key held all the way
+        let mut finished = Vec::new();
+        for _ in 0..16 {
+            walk.intent = Some(Facing::Down);
+            finished.extend(walk.update(&map, &mut position, &mut facing, &mut Vec::new(), 0.05));
+            assert!(walk.is_stepping());
+        }
+
+        let tiles: Vec<_> = (1..=3).map(|y| GridPosition::new(0, y)).collect();
+        assert_eq!(finished, tiles);
+        assert_eq!(facing, Facing::Down);
+    }
+
+    #[test]
+    fn blocked_steps_only_turn() {
+        let map = Map::new();
+        let mut walk = Walk::new(4.0, 0);
+        let mut position = GridPosition::new(0, 0);
+        let mut facing = Facing::Down;
+
+        walk.intent = Some(Facing::Left);
+        let finished = walk.update(&map, &mut position, &mut facing, &mut Vec::new(), 1.0);
+        assert_eq!(finished, None);
+        assert_eq!(position, GridPosition::new(0, 0));
+        assert_eq!(facing, Facing::Left);
+        assert!(!walk.is_stepping());
+    }
+}
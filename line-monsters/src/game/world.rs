@@ -0,0 +1,210 @@
+//! Everything in the world that isn't terrain, such as players, NPCs, monsters and items,
+//! kept as entities in an ECS. Systems are run once per frame by [`World::tick`].
+
+use super::map::Map;
+use super::walk::Walk;
+use crate::renderer::animation::Animation;
+use crate::renderer::spritebatch::{BlendMode, UvRect};
+use ellecs::world::EntityBuilder;
+use ultraviolet::{Vec2, Vec3};
+
+/// The tile an entity is on, in the same coordinates as [`Map::get_tile`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GridPosition {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl GridPosition {
+    pub fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
+    /// The neighbouring tile in `facing`
+    pub fn step(self, facing: Facing) -> Self {
+        let (x, y) = facing.offset();
+        Self::new(self.x + x, self.y + y)
+    }
+}
+
+/// The height of the tile under an entity, kept up to date by [`World::tick`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Height(pub u8);
+
+/// Which way an entity is looking. Up is away from the camera, towards negative y.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Facing {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Facing {
+    pub const ALL: [Facing; 4] = [Facing::Up, Facing::Down, Facing::Left, Facing::Right];
+
+    /// The change in grid position of a step in this direction
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Facing::Up => (0, -1),
+            Facing::Down => (0, 1),
+            Facing::Left => (-1, 0),
+            Facing::Right => (1, 0),
+        }
+    }
+
+    /// How sprite sheet clips name the direction, see [`Animated`]
+    pub fn name(self) -> &'static str {
+        match self {
+            Facing::Up => "up",
+            Facing::Down => "down",
+            Facing::Left => "left",
+            Facing::Right => "right",
+        }
+    }
+}
+
+/// Entities with this can't share a tile with other entities that have it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Collision;
+
+/// Drawn standing on the entity's tile
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sprite {
+    /// Path in the assets directory
+    pub texture: String,
+    /// Width and height in world units
+    pub size: Vec2,
+    pub uv: UvRect,
+    pub mode: BlendMode,
+    /// Moves the sprite away from its tile, such as partway through a step
+    pub offset: Vec3,
+}
+
+impl Sprite {
+    /// Shows the whole texture with alpha cutout, as most sprites have transparent edges
+    pub fn new(texture: impl Into<String>, size: Vec2) -> Self {
+        Self {
+            texture: texture.into(),
+            size,
+            uv: UvRect::FULL,
+            mode: BlendMode::Cutout,
+            offset: Vec3::zero(),
+        }
+    }
+}
+
+/// Picks the [`Sprite`]'s frame out of a sprite sheet. Plays the sheet's `walk_` clip while
+/// the entity steps and its `idle_` clip otherwise, followed by the way it's facing, such
+/// as `walk_left`.
+#[derive(Clone, Debug)]
+pub struct Animated {
+    /// Path in the assets directory of the sheet's RON file, its frames are rects of the
+    /// sprite's texture
+    pub sheet: String,
+    pub animation: Animation,
+}
+
+impl Animated {
+    pub fn new(sheet: impl Into<String>) -> Self {
+        Self {
+            sheet: sheet.into(),
+            animation: Animation::new("idle_down"),
+        }
+    }
+}
+
+/// Where the top of a floor tile of `height` at `position` is in world space
+pub fn world_position(position: GridPosition, height: Height) -> Vec3 {
+    Vec3::new(position.x as f32, height.0 as f32 - 0.5, position.y as f32)
+}
+
+pub struct World {
+    pub(super) entities: ellecs::world::World,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            entities: ellecs::world::World::new(),
+        }
+    }
+
+    /// Add components with `.with(component)` and finish with `.build()`
+    pub fn spawn(&mut self) -> EntityBuilder<'_> {
+        self.entities.spawn()
+    }
+
+    /// Runs every system, `dt` is the seconds since the last tick
+    pub fn tick(&mut self, map: &Map, dt: f32) {
+        self.walk(map, dt);
+        self.animate(dt);
+        self.follow_terrain(map);
+    }
+
+    /// Moves entities with a [`Walk`] and offsets their sprites to match
+    fn walk(&mut self, map: &Map, dt: f32) {
+        // Collected up front as the walking query borrows every position mutably
+        let mut occupied = self.occupied();
+
+        let query = self
+            .entities
+            .query::<(&mut GridPosition, &mut Facing, &mut Walk, &mut Sprite)>();
+        for (position, facing, walk, sprite) in query.borrow().into_iter() {
+            walk.update(map, position, facing, &mut occupied, dt);
+            sprite.offset = walk.offset();
+        }
+    }
+
+    /// Switches animated entities between walking and standing still
+    fn animate(&mut self, dt: f32) {
+        let query = self.entities.query::<(&Facing, &Walk, &mut Animated)>();
+        for (facing, walk, animated) in query.borrow().into_iter() {
+            let action = if walk.is_stepping() { "walk" } else { "idle" };
+            animated
+                .animation
+                .play(&format!("{}_{}", action, facing.name()));
+            animated.animation.update(dt);
+        }
+    }
+
+    /// Keeps entities standing on top of the tile under them, entities off the
+    /// edge of the map keep their last height
+    fn follow_terrain(&mut self, map: &Map) {
+        let query = self.entities.query::<(&GridPosition, &mut Height)>();
+        for (position, height) in query.borrow().into_iter() {
+            if let Some(tile) = map.get_tile(position.x, position.y) {
+                height.0 = tile.height;
+            }
+        }
+    }
+
+    /// Every tile an entity with [`Collision`] is on
+    fn occupied(&self) -> Vec<GridPosition> {
+        let query = self.entities.query::<(&GridPosition, &Collision)>();
+        let mut occupied = Vec::new();
+        for (position, _) in query.borrow().into_iter() {
+            occupied.push(*position);
+        }
+        occupied
+    }
+
+    /// Points the uv of every animated sprite at its current frame. `frame` finds it in
+    /// the sheet, the uv is left as it is when that returns `None`.
+    pub fn update_frames(&mut self, mut frame: impl FnMut(&Sprite, &Animated) -> Option<UvRect>) {
+        let query = self.entities.query::<(&mut Sprite, &Animated)>();
+        for (sprite, animated) in query.borrow().into_iter() {
+            if let Some(uv) = frame(sprite, animated) {
+                sprite.uv = uv;
+            }
+        }
+    }
+
+    /// Calls `draw` with the world position and sprite of every entity that has one
+    pub fn for_each_sprite(&self, mut draw: impl FnMut(Vec3, &Sprite)) {
+        let query = self.entities.query::<(&GridPosition, &Height, &Sprite)>();
+        for (position, height, sprite) in query.borrow().into_iter() {
+            draw(world_position(*position, *height) + sprite.offset, sprite);
+        }
+    }
+}
@@ -0,0 +1,484 @@
+pub mod recording;
+
+use nanoserde::{DeBin, DeRon, SerBin};
+use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+
+macro_rules! actions {
+    [$($action: ident => [$($binding: expr),*]),* $(,)?] => {
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum Action {
+            $($action,)*
+        }
+
+        impl Action {
+            pub const ALL: &'static [Action] = &[$(Action::$action,)*];
+            pub const COUNT: usize = Self::ALL.len();
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Action::$action => stringify!($action),)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($action) => Some(Action::$action),)*
+                    _ => None,
+                }
+            }
+
+            fn default_bindings(&self) -> Vec<Binding> {
+                match self {
+                    $(Action::$action => vec![$($binding,)*],)*
+                }
+            }
+        }
+    };
+}
+
+actions![
+    Quit => [Binding::Key(VirtualKeyCode::Escape)],
+
+    CameraForward => [Binding::Key(VirtualKeyCode::W)],
+    CameraBackward => [Binding::Key(VirtualKeyCode::S)],
+    CameraLeft => [Binding::Key(VirtualKeyCode::A)],
+    CameraRight => [Binding::Key(VirtualKeyCode::D)],
+
+    LightLeft => [Binding::Key(VirtualKeyCode::Q)],
+    LightRight => [Binding::Key(VirtualKeyCode::E)],
+    ToggleShadows => [Binding::Key(VirtualKeyCode::F2)],
+
+    CursorUp => [Binding::Key(VirtualKeyCode::Up)],
+    CursorDown => [Binding::Key(VirtualKeyCode::Down)],
+    CursorLeft => [Binding::Key(VirtualKeyCode::Left)],
+    CursorRight => [Binding::Key(VirtualKeyCode::Right)],
+
+    MoveUp => [Binding::Key(VirtualKeyCode::Up)],
+    MoveDown => [Binding::Key(VirtualKeyCode::Down)],
+    MoveLeft => [Binding::Key(VirtualKeyCode::Left)],
+    MoveRight => [Binding::Key(VirtualKeyCode::Right)],
+
+    RaiseTile => [Binding::Key(VirtualKeyCode::Space)],
+    LowerTile => [Binding::Key(VirtualKeyCode::C)],
+    SwapModel => [Binding::Key(VirtualKeyCode::X)],
+    SwapMaterial => [Binding::Key(VirtualKeyCode::T)],
+    SwapPassability => [Binding::Key(VirtualKeyCode::B)],
+    SwapZone => [Binding::Key(VirtualKeyCode::Z)],
+    RotateTile => [Binding::Key(VirtualKeyCode::R)],
+    SetSpawn => [Binding::Key(VirtualKeyCode::P)],
+    ShowPassability => [Binding::Key(VirtualKeyCode::F3)],
+    SaveMap => [Binding::Key(VirtualKeyCode::Return)],
+
+    TogglePlay => [Binding::Key(VirtualKeyCode::F5)],
+];
+
+macro_rules! key_names {
+    [$($key: ident),* $(,)?] => {
+        fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($key) => Some(VirtualKeyCode::$key),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+key_names![
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Escape, Insert, Home, Delete, End, PageDown, PageUp,
+    Left, Up, Right, Down,
+    Back, Return, Space, Tab,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadEnter, NumpadComma, NumpadEquals,
+    LAlt, LControl, LShift, LWin, RAlt, RControl, RShift, RWin,
+    Apostrophe, Backslash, Colon, Comma, Equals, Grave, LBracket, Minus, Period,
+    RBracket, Semicolon, Slash,
+];
+
+/// A physical input that can be bound to an [`Action`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    /// Keys use their `VirtualKeyCode` name (`"W"`, `"Space"`, `"Key1"`),
+    /// mouse buttons are `"MouseLeft"`, `"MouseRight"` and `"MouseMiddle"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "MouseLeft" => Some(Binding::Mouse(MouseButton::Left)),
+            "MouseRight" => Some(Binding::Mouse(MouseButton::Right)),
+            "MouseMiddle" => Some(Binding::Mouse(MouseButton::Middle)),
+            _ => key_from_name(name).map(Binding::Key),
+        }
+    }
+}
+
+/// How a held action repeats, in seconds until the first repeat and repeats per second after that
+#[derive(DeRon, DeBin, SerBin, Copy, Clone, Debug)]
+pub struct Repeat {
+    pub delay: f32,
+    pub rate: f32,
+}
+
+impl Repeat {
+    pub const CURSOR: Repeat = Repeat {
+        delay: 0.25,
+        rate: 12.0,
+    };
+    pub const TILE_HEIGHT: Repeat = Repeat {
+        delay: 0.4,
+        rate: 5.0,
+    };
+
+    /// Number of repeats that have fired after the action has been held for `held_for` seconds
+    fn count(&self, held_for: f32) -> u32 {
+        if held_for < self.delay || self.rate <= 0.0 {
+            0
+        } else {
+            ((held_for - self.delay) * self.rate) as u32 + 1
+        }
+    }
+}
+
+impl Action {
+    fn default_repeat(&self) -> Option<Repeat> {
+        match self {
+            Action::CursorUp | Action::CursorDown | Action::CursorLeft | Action::CursorRight => {
+                Some(Repeat::CURSOR)
+            }
+            Action::RaiseTile | Action::LowerTile => Some(Repeat::TILE_HEIGHT),
+            _ => None,
+        }
+    }
+}
+
+#[derive(DeRon)]
+struct BindingConfig {
+    action: String,
+    inputs: Option<Vec<String>>,
+    repeat: Option<Repeat>,
+}
+
+#[derive(DeRon)]
+struct InputConfig {
+    repeat: Option<Repeat>,
+    bindings: Vec<BindingConfig>,
+}
+
+pub struct InputMap {
+    bindings: Vec<(Binding, Action)>,
+    repeats: [Option<Repeat>; Action::COUNT],
+}
+
+impl InputMap {
+    pub const CONFIG_PATH: &'static str = "input.ron";
+
+    pub fn new() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .flat_map(|action| {
+                action
+                    .default_bindings()
+                    .into_iter()
+                    .map(move |binding| (binding, *action))
+            })
+            .collect();
+
+        let mut repeats = [None; Action::COUNT];
+        for action in Action::ALL.iter() {
+            repeats[*action as usize] = action.default_repeat();
+        }
+
+        Self { bindings, repeats }
+    }
+
+    /// Loads the default bindings and then replaces the bindings of every action
+    /// listed in the config file. A missing file leaves the defaults untouched.
+    ///
+    /// A top level `repeat` replaces the repeat of every action that repeats by default,
+    /// a `repeat` on a binding makes that action repeat when held.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut map = Self::new();
+
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(map),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+
+        let config =
+            InputConfig::deserialize_ron(&src).map_err(|e| format!("{}: {:?}", path, e))?;
+
+        if let Some(repeat) = config.repeat {
+            for action_repeat in map.repeats.iter_mut() {
+                if action_repeat.is_some() {
+                    *action_repeat = Some(repeat);
+                }
+            }
+        }
+
+        for binding_config in config.bindings.iter() {
+            let action = Action::from_name(&binding_config.action)
+                .ok_or_else(|| format!("{}: unknown action `{}`", path, binding_config.action))?;
+
+            if let Some(inputs) = &binding_config.inputs {
+                let bindings = inputs
+                    .iter()
+                    .map(|input| {
+                        Binding::from_name(input)
+                            .ok_or_else(|| format!("{}: unknown input `{}`", path, input))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                map.rebind(action, &bindings);
+            }
+
+            if let Some(repeat) = binding_config.repeat {
+                map.repeats[action as usize] = Some(repeat);
+            }
+        }
+
+        Ok(map)
+    }
+
+    pub fn rebind(&mut self, action: Action, bindings: &[Binding]) {
+        self.bindings.retain(|(_, bound)| *bound != action);
+        self.bindings
+            .extend(bindings.iter().map(|binding| (*binding, action)));
+    }
+
+    pub fn repeat(&self, action: Action) -> Option<Repeat> {
+        self.repeats[action as usize]
+    }
+
+    pub fn set_repeat(&mut self, action: Action, repeat: Option<Repeat>) {
+        self.repeats[action as usize] = repeat;
+    }
+
+    fn actions_for(&self, binding: Binding) -> impl Iterator<Item = Action> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(bound, _)| *bound == binding)
+            .map(|(_, action)| *action)
+    }
+}
+
+/// Tracks the state of every [`Action`] between two calls to [`Input::end_frame`]
+pub struct Input {
+    pub map: InputMap,
+
+    held: [bool; Action::COUNT],
+    pressed: [bool; Action::COUNT],
+    released: [bool; Action::COUNT],
+
+    /// Bindings that are down, so the OS repeating a held key isn't counted again
+    bindings_down: Vec<Binding>,
+    /// How many of each action's bindings are down, the action is held while any are
+    bindings_held: [u8; Action::COUNT],
+
+    /// Seconds each action has been held for, advanced by [`Input::update`]
+    held_for: [f32; Action::COUNT],
+    /// How many times each action fired this frame, see [`Input::repeats`]
+    repeats: [u32; Action::COUNT],
+
+    /// Every press and release since the last [`Input::end_frame`], in order
+    changes: Vec<(Action, bool)>,
+}
+
+impl Input {
+    pub fn new(map: InputMap) -> Self {
+        Self {
+            map,
+
+            held: [false; Action::COUNT],
+            pressed: [false; Action::COUNT],
+            released: [false; Action::COUNT],
+
+            bindings_down: Vec::new(),
+            bindings_held: [0; Action::COUNT],
+
+            held_for: [0.0; Action::COUNT],
+            repeats: [0; Action::COUNT],
+
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        let (binding, state) = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => (Binding::Key(*keycode), *state),
+            WindowEvent::MouseInput { state, button, .. } => (Binding::Mouse(*button), *state),
+            _ => return false,
+        };
+
+        self.set_binding(binding, state == ElementState::Pressed)
+    }
+
+    /// Presses or releases `binding`, returning whether it's bound to anything
+    fn set_binding(&mut self, binding: Binding, is_pressed: bool) -> bool {
+        let actions: Vec<_> = self.map.actions_for(binding).collect();
+        if is_pressed == self.bindings_down.contains(&binding) {
+            return !actions.is_empty();
+        }
+
+        if is_pressed {
+            self.bindings_down.push(binding);
+        } else {
+            self.bindings_down.retain(|down| *down != binding);
+        }
+        for action in actions.iter() {
+            let held = &mut self.bindings_held[*action as usize];
+            *held = if is_pressed {
+                held.saturating_add(1)
+            } else {
+                held.saturating_sub(1)
+            };
+            let is_held = *held > 0;
+            self.set_action(*action, is_held);
+        }
+        !actions.is_empty()
+    }
+
+    /// Sets the state of `action` directly, whatever its bindings are doing
+    pub fn set_action(&mut self, action: Action, is_pressed: bool) {
+        let index = action as usize;
+        if is_pressed && !self.held[index] {
+            self.pressed[index] = true;
+        }
+        if !is_pressed && self.held[index] {
+            self.released[index] = true;
+        }
+        if is_pressed != self.held[index] {
+            self.changes.push((action, is_pressed));
+        }
+        self.held[index] = is_pressed;
+    }
+
+    /// Advances held actions by `dt` seconds of game time, call once per frame before the game ticks
+    pub fn update(&mut self, dt: f32) {
+        for action in Action::ALL.iter() {
+            let index = *action as usize;
+
+            if self.pressed[index] {
+                self.held_for[index] = 0.0;
+                self.repeats[index] = 1;
+            } else if self.held[index] {
+                let before = self.held_for[index];
+                let after = before + dt;
+                self.held_for[index] = after;
+
+                if let Some(repeat) = self.map.repeats[index] {
+                    self.repeats[index] = repeat.count(after) - repeat.count(before);
+                }
+            }
+        }
+    }
+
+    /// Clears the pressed and released state, call once per frame after the game has ticked
+    pub fn end_frame(&mut self) {
+        self.pressed = [false; Action::COUNT];
+        self.released = [false; Action::COUNT];
+        self.repeats = [0; Action::COUNT];
+        self.changes.clear();
+    }
+
+    /// True only on the frame the action went down
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed[action as usize]
+    }
+
+    /// True for every frame the action is down
+    pub fn held(&self, action: Action) -> bool {
+        self.held[action as usize]
+    }
+
+    /// How many times the action fired this frame, once on the frame it went down and then
+    /// at the action's [`Repeat`] rate while it is held. A long frame can fire several
+    /// repeats at once. Actions without a repeat fire like [`Input::pressed`].
+    pub fn repeats(&self, action: Action) -> u32 {
+        self.repeats[action as usize]
+    }
+
+    /// True only on the frame the action went up
+    pub fn released(&self, action: Action) -> bool {
+        self.released[action as usize]
+    }
+
+    pub fn changes(&self) -> &[(Action, bool)] {
+        &self.changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_is_held_while_any_binding_is() {
+        let mut map = InputMap::new();
+        let keys = [
+            Binding::Key(VirtualKeyCode::W),
+            Binding::Key(VirtualKeyCode::I),
+        ];
+        map.rebind(Action::CameraForward, &keys);
+        let mut input = Input::new(map);
+
+        input.set_binding(keys[0], true);
+        input.set_binding(keys[1], true);
+        // The OS repeating a held key doesn't count as another binding going down
+        input.set_binding(keys[1], true);
+        input.set_binding(keys[0], false);
+        assert!(input.held(Action::CameraForward));
+        assert!(!input.released(Action::CameraForward));
+
+        input.set_binding(keys[1], false);
+        assert!(!input.held(Action::CameraForward));
+        assert!(input.released(Action::CameraForward));
+        assert_eq!(
+            input.changes(),
+            &[
+                (Action::CameraForward, true),
+                (Action::CameraForward, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn long_frames_fire_every_repeat_they_cover() {
+        let mut map = InputMap::new();
+        map.set_repeat(
+            Action::CursorUp,
+            Some(Repeat {
+                delay: 0.5,
+                rate: 10.0,
+            }),
+        );
+        let mut input = Input::new(map);
+
+        input.set_action(Action::CursorUp, true);
+        input.update(0.1);
+        assert_eq!(input.repeats(Action::CursorUp), 1);
+        input.end_frame();
+
+        input.update(0.3);
+        assert_eq!(input.repeats(Action::CursorUp), 0);
+        input.end_frame();
+
+        // Held for 0.75s, past the delay and then two and a half intervals
+        input.update(0.45);
+        assert_eq!(input.repeats(Action::CursorUp), 3);
+    }
+}
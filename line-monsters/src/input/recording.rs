@@ -0,0 +1,289 @@
+use super::{Action, Input, InputMap, Repeat};
+use crate::game::data::GameData;
+use crate::game::editor::Editor;
+use crate::game::encounter::Encounters;
+use crate::game::play::{Play, WildBattle};
+use crate::game::rng::Rng;
+use crate::game::settings::PlaySettings;
+use nanoserde::{DeBin, SerBin};
+use ultraviolet::Vec3;
+
+#[derive(DeBin, SerBin, Clone, Debug)]
+pub struct RecordedChange {
+    /// Stored by name so recordings survive reordering [`Action`]
+    pub action: String,
+    pub pressed: bool,
+}
+
+#[derive(DeBin, SerBin, Clone, Debug)]
+pub struct RecordedFrame {
+    /// Seconds of game time since the recording started, at the end of this frame
+    pub time: f64,
+    pub dt: f32,
+    pub changes: Vec<RecordedChange>,
+}
+
+#[derive(DeBin, SerBin, Clone, Debug)]
+pub struct RecordedRepeat {
+    pub action: String,
+    pub repeat: Repeat,
+}
+
+/// Every frame of a session as the [`Action`] changes and frame time the game saw,
+/// independent of key bindings so a replay behaves the same on any config
+#[derive(DeBin, SerBin, Clone, Debug)]
+pub struct Recording {
+    /// The repeat settings the session was recorded with, held actions depend on them
+    pub repeats: Vec<RecordedRepeat>,
+    /// Whether the session started in play mode, as with `--play`
+    pub playing: bool,
+    /// What the session's play tests were seeded with, see [`Play::rngs`]
+    pub seed: u64,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new(map: &InputMap, playing: bool, seed: u64) -> Self {
+        let repeats = Action::ALL
+            .iter()
+            .filter_map(|action| {
+                map.repeat(*action).map(|repeat| RecordedRepeat {
+                    action: action.name().to_string(),
+                    repeat,
+                })
+            })
+            .collect();
+
+        Self {
+            repeats,
+            playing,
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Call once per frame after [`Input::update`] and before [`Input::end_frame`]
+    pub fn record_frame(&mut self, dt: f32, input: &Input) {
+        let time = self.frames.last().map_or(0.0, |frame| frame.time) + dt as f64;
+
+        let changes = input
+            .changes()
+            .iter()
+            .map(|(action, pressed)| RecordedChange {
+                action: action.name().to_string(),
+                pressed: *pressed,
+            })
+            .collect();
+
+        self.frames.push(RecordedFrame { time, dt, changes });
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let bytes = SerBin::serialize_bin(self);
+        std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        DeBin::deserialize_bin(&bytes).map_err(|e| format!("{}: {:?}", path, e))
+    }
+
+    /// Feeds the recorded frames through a fresh [`Input`] one at a time
+    pub fn frames(&self) -> Result<Replay<'_>, String> {
+        let mut map = InputMap::new();
+        for action in Action::ALL.iter() {
+            map.set_repeat(*action, None);
+        }
+        for recorded in self.repeats.iter() {
+            let action = Action::from_name(&recorded.action)
+                .ok_or_else(|| format!("unknown action `{}` in recording", recorded.action))?;
+            map.set_repeat(action, Some(recorded.repeat));
+        }
+
+        let mut frames = Vec::with_capacity(self.frames.len());
+        for frame in self.frames.iter() {
+            let changes = frame
+                .changes
+                .iter()
+                .map(|change| {
+                    Action::from_name(&change.action)
+                        .map(|action| (action, change.pressed))
+                        .ok_or_else(|| format!("unknown action `{}` in recording", change.action))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            frames.push((frame, changes));
+        }
+
+        Ok(Replay {
+            frames: frames.into_iter(),
+            input: Input::new(map),
+        })
+    }
+
+    /// Runs the recording against `editor` without a window or GPU, switching between
+    /// editing and play tests as the session did. Returns the battles fought in play.
+    pub fn replay(
+        &self,
+        editor: &mut Editor,
+        data: &GameData,
+        encounters: &Encounters,
+        settings: &PlaySettings,
+    ) -> Result<Vec<WildBattle>, String> {
+        let mut replay = self.frames()?;
+        let mut play_rng = Play::rngs(self.seed);
+        let start =
+            |editor: &Editor, rng: Rng| Play::start(&editor.map, Vec3::zero(), rng, settings, data);
+
+        let mut play = if self.playing {
+            Some(start(editor, play_rng())?)
+        } else {
+            None
+        };
+        let mut battles = Vec::new();
+        while let Some((dt, input)) = replay.next_frame() {
+            if input.pressed(Action::TogglePlay) {
+                play = match play {
+                    Some(_) => None,
+                    None => Some(start(editor, play_rng())?),
+                };
+            }
+            match &mut play {
+                Some(play) => {
+                    battles.extend(play.update(&editor.map, encounters, data, input, dt)?)
+                }
+                None => editor.update(input)?,
+            }
+        }
+        Ok(battles)
+    }
+}
+
+pub struct Replay<'a> {
+    frames: std::vec::IntoIter<(&'a RecordedFrame, Vec<(Action, bool)>)>,
+    input: Input,
+}
+
+impl Replay<'_> {
+    /// Advances to the next recorded frame and returns its length in seconds and the
+    /// input the game saw on it
+    pub fn next_frame(&mut self) -> Option<(f32, &Input)> {
+        let (frame, changes) = self.frames.next()?;
+
+        self.input.end_frame();
+        for (action, pressed) in changes {
+            self.input.set_action(action, pressed);
+        }
+        self.input.update(frame.dt);
+
+        Some((frame.dt, &self.input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::Map;
+
+    fn data() -> GameData {
+        GameData::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../data")).unwrap()
+    }
+
+    /// Plays `frames` of action changes through the editor the way the game loop does,
+    /// recording them as it goes
+    fn record(editor: &mut Editor, frames: &[(f32, &[(Action, bool)])]) -> Recording {
+        let mut input = Input::new(InputMap::new());
+        let mut recording = Recording::new(&input.map, false, 0);
+        for (dt, changes) in frames {
+            for (action, pressed) in changes.iter() {
+                input.set_action(*action, *pressed);
+            }
+            input.update(*dt);
+            editor.update(&input).unwrap();
+            recording.record_frame(*dt, &input);
+            input.end_frame();
+        }
+        recording
+    }
+
+    #[test]
+    fn replay_matches_recorded_session() {
+        let mut editor = Editor::new(Map::new(), "unused.blob");
+        let recording = record(
+            &mut editor,
+            &[
+                (0.016, &[(Action::CursorRight, true)]),
+                // Long enough for the cursor to repeat a few times
+                (0.5, &[]),
+                (
+                    0.016,
+                    &[(Action::CursorRight, false), (Action::RaiseTile, true)],
+                ),
+                (
+                    0.016,
+                    &[(Action::RaiseTile, false), (Action::SwapMaterial, true)],
+                ),
+                (0.016, &[(Action::SwapMaterial, false)]),
+            ],
+        );
+        assert_ne!(editor.snapshot(), Editor::new(Map::new(), "").snapshot());
+
+        // Through bytes, as it would be saved with `--record`
+        let recording: Recording =
+            DeBin::deserialize_bin(&SerBin::serialize_bin(&recording)).unwrap();
+        let mut replayed = Editor::new(Map::new(), "unused.blob");
+        let battles = recording
+            .replay(
+                &mut replayed,
+                &data(),
+                &Encounters::default(),
+                &PlaySettings::default(),
+            )
+            .unwrap();
+        assert!(battles.is_empty());
+
+        assert_eq!(replayed.selected, editor.selected);
+        assert_eq!(replayed.snapshot(), editor.snapshot());
+    }
+
+    #[test]
+    fn replay_starts_in_the_recorded_mode() {
+        // Raises the tile once before switching modes and twice after, only the raises
+        // in edit mode take
+        let frames: &[&[(Action, bool)]] = &[
+            &[(Action::RaiseTile, true)],
+            &[(Action::RaiseTile, false), (Action::TogglePlay, true)],
+            &[(Action::TogglePlay, false), (Action::RaiseTile, true)],
+            &[(Action::RaiseTile, false)],
+            &[(Action::RaiseTile, true)],
+        ];
+        let data = data();
+        let height_after = |playing: bool| {
+            let mut input = Input::new(InputMap::new());
+            let mut recording = Recording::new(&input.map, playing, 7);
+            for changes in frames {
+                for (action, pressed) in changes.iter() {
+                    input.set_action(*action, *pressed);
+                }
+                input.update(0.016);
+                recording.record_frame(0.016, &input);
+                input.end_frame();
+            }
+
+            let mut editor = Editor::new(Map::new(), "unused.blob");
+            recording
+                .replay(
+                    &mut editor,
+                    &data,
+                    &Encounters::default(),
+                    &PlaySettings::default(),
+                )
+                .unwrap();
+            let (x, y) = editor.selected;
+            editor.map.get_tile(x, y).unwrap().height
+        };
+
+        let start = Map::new().get_tile(0, 0).unwrap().height;
+        assert_eq!(height_after(false), start + 1);
+        assert_eq!(height_after(true), start + 2);
+    }
+}
@@ -1,3 +1,11 @@
+// The `DeRon` derive expands to matches that clippy would rather see written with `?`
+#![allow(clippy::question_mark)]
+// `map_or(false, ..)` and `map_or(true, ..)` build on compilers older than the
+// `is_some_and` and `is_none_or` clippy suggests
+#![allow(clippy::unnecessary_map_or)]
+
+use std::path::Path;
+use std::time::Instant;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -6,54 +14,412 @@ use winit::{
 
 mod camera;
 mod game;
+mod input;
 
-pub mod models;
 pub mod renderer;
 
+/// The value following `name` on the command line
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
+
+/// Whether `name` is on the command line
+fn has_arg(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
+/// `--seed` when it's given so play tests can be repeated exactly, one from the clock
+/// otherwise
+fn seed_arg() -> u64 {
+    match arg_value("--seed").map(|seed| seed.parse()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(e)) => {
+            eprintln!("Invalid --seed, seeding from the clock: {}", e);
+            game::rng::Rng::time_seed()
+        }
+        None => game::rng::Rng::time_seed(),
+    }
+}
+
+/// Runs a recording made with `--record` against the saved map without opening a window
+/// and writes the resulting map to `--snapshot` (default `replay_snapshot.blob`). The
+/// wild monsters its play tests ran into are printed.
+fn replay(recording_path: &str) {
+    let recording = match input::recording::Recording::load(recording_path) {
+        Ok(recording) => recording,
+        Err(e) => {
+            eprintln!("Failed to load recording: {}", e);
+            return;
+        }
+    };
+
+    let snapshot_path =
+        arg_value("--snapshot").unwrap_or_else(|| "replay_snapshot.blob".to_string());
+
+    // Saves during the replay go to the snapshot rather than over the real map
+    let map = match game::map::Map::load(Path::new(game::map::Map::PATH)) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Failed to load map: {}", e);
+            return;
+        }
+    };
+    let mut editor = game::editor::Editor::new(map, &snapshot_path);
+
+    let data = match game::data::GameData::load(game::data::DIR) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to load game data:\n{}", e);
+            return;
+        }
+    };
+    let encounters =
+        match game::encounter::Encounters::load(game::encounter::Encounters::PATH, &data.species) {
+            Ok(encounters) => encounters,
+            Err(e) => {
+                eprintln!("Failed to load encounters: {}", e);
+                return;
+            }
+        };
+    let mut play_settings =
+        match game::settings::PlaySettings::load(game::settings::PlaySettings::CONFIG_PATH) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Failed to load play settings: {}", e);
+                return;
+            }
+        };
+    if let Err(e) = play_settings.override_from_args(arg_value) {
+        eprintln!("Invalid play setting: {}", e);
+        return;
+    }
+
+    match recording.replay(&mut editor, &data, &encounters, &play_settings) {
+        Ok(battles) => {
+            for battle in battles {
+                println!("{}", battle);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to replay {}: {}", recording_path, e);
+            return;
+        }
+    }
+
+    if let Err(e) = std::fs::write(&snapshot_path, editor.snapshot()) {
+        eprintln!("Failed to write snapshot: {}: {}", snapshot_path, e);
+    }
+}
+
+/// The AI difficulty given as `name` on the command line, [`Difficulty::Random`] when it
+/// isn't
+///
+/// [`Difficulty::Random`]: game::ai::Difficulty::Random
+fn difficulty_arg(name: &str) -> Result<game::ai::Difficulty, String> {
+    match arg_value(name) {
+        Some(difficulty) => game::ai::Difficulty::from_name(&difficulty)
+            .ok_or_else(|| format!("Invalid {}: unknown difficulty `{}`", name, difficulty)),
+        None => Ok(game::ai::Difficulty::Random),
+    }
+}
+
+/// Plays `count` battles without opening a window and prints how often each side and
+/// each species won. Both sides are single random species at the same random level,
+/// fought by the AIs given with `--ai` and `--opponent-ai`. With `--trainer` the second
+/// side is that trainer's party and AI instead, against random species at the same
+/// levels. Seeded from `--seed` like play tests.
+fn simulate_battles(count: u32) {
+    let data = match game::data::GameData::load(game::data::DIR) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to load game data:\n{}", e);
+            return;
+        }
+    };
+
+    let mut difficulties = match (difficulty_arg("--ai"), difficulty_arg("--opponent-ai")) {
+        (Ok(ai), Ok(opponent_ai)) => [ai, opponent_ai],
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let trainer = match arg_value("--trainer") {
+        Some(name) => match data.trainers.find(&name) {
+            Some(index) => Some(&data.trainers.trainers[index]),
+            None => {
+                eprintln!("Invalid --trainer: unknown trainer `{}`", name);
+                return;
+            }
+        },
+        None => None,
+    };
+    if let Some(trainer) = trainer {
+        difficulties[1] = trainer.difficulty;
+    }
+
+    let mut rng = game::rng::Rng::new(seed_arg());
+    let simulation = match game::simulation::simulate(&data, difficulties, trainer, count, &mut rng)
+    {
+        Ok(simulation) => simulation,
+        Err(e) => {
+            eprintln!("Failed to simulate battles: {}", e);
+            return;
+        }
+    };
+
+    for (side, won) in simulation.wins.iter().enumerate() {
+        let name = match (side, trainer) {
+            (1, Some(trainer)) => trainer.name.clone(),
+            _ => format!("Side {}", side + 1),
+        };
+        println!(
+            "{:<16} won {:>6} of {:>6} battles ({:?})",
+            name, won, simulation.battles, difficulties[side]
+        );
+    }
+    println!("{} draws", simulation.draws);
+    println!();
+    for (species, (fought, won)) in data.species.species.iter().zip(simulation.species) {
+        let rate = if fought == 0 {
+            0.0
+        } else {
+            won as f32 / fought as f32 * 100.0
+        };
+        println!(
+            "{:<16} won {:>6} of {:>6} battles ({:.1}%)",
+            species.name, won, fought, rate
+        );
+    }
+}
+
 fn main() {
     env_logger::init();
 
+    if let Some(recording_path) = arg_value("--replay") {
+        replay(&recording_path);
+        return;
+    }
+    if let Some(count) = arg_value("--simulate-battles") {
+        match count.parse() {
+            Ok(count) => simulate_battles(count),
+            Err(e) => eprintln!("Invalid --simulate-battles: {}: {}", count, e),
+        }
+        return;
+    }
+
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let window = WindowBuilder::new()
+        .with_title("line-monsters")
+        .build(&event_loop)
+        .unwrap();
 
     use futures::executor::block_on;
 
-    let mut state = block_on(renderer::State::new(&window));
-    let mut scene = game::Scene::new(state.device.clone(), &state.queue);
+    let input_map = input::InputMap::load(input::InputMap::CONFIG_PATH).unwrap_or_else(|e| {
+        eprintln!("Failed to load input bindings, using defaults: {}", e);
+        input::InputMap::new()
+    });
+    let mut input = input::Input::new(input_map);
+
+    // Play tests are seeded from this in turn, so a recording can roll the same
+    let seed = seed_arg();
+    let mut play_rng = game::play::Play::rngs(seed);
+
+    let mut recording = arg_value("--record").map(|path| {
+        let recording = input::recording::Recording::new(&input.map, has_arg("--play"), seed);
+        (path, recording)
+    });
+
+    let mut editor = game::editor::Editor::open(game::map::Map::PATH).unwrap_or_else(|e| {
+        eprintln!("Failed to load map, starting a new one: {}", e);
+        game::editor::Editor::new(game::map::Map::new(), game::map::Map::PATH)
+    });
+
+    let mut graphics_settings = renderer::settings::GraphicsSettings::load(
+        renderer::settings::GraphicsSettings::CONFIG_PATH,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to load graphics settings, using defaults: {}", e);
+        renderer::settings::GraphicsSettings::default()
+    });
+    if let Err(e) = graphics_settings.override_from_args(arg_value) {
+        eprintln!("Invalid graphics setting: {}", e);
+        return;
+    }
+
+    let mut play_settings =
+        game::settings::PlaySettings::load(game::settings::PlaySettings::CONFIG_PATH)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load play settings, using defaults: {}", e);
+                game::settings::PlaySettings::default()
+            });
+    if let Err(e) = play_settings.override_from_args(arg_value) {
+        eprintln!("Invalid play setting: {}", e);
+        return;
+    }
+
+    let mut state = match block_on(renderer::State::new(&window, graphics_settings)) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Failed to start the renderer: {}", e);
+            return;
+        }
+    };
+    let mut scene = match game::Scene::new(state.device.clone(), &mut state.assets) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Failed to load assets: {}", e);
+            return;
+        }
+    };
+
+    let data = game::data::GameData::load(game::data::DIR).unwrap_or_else(|e| {
+        eprintln!("Failed to load game data:\n{}", e);
+        game::data::GameData::default()
+    });
+    let encounters =
+        game::encounter::Encounters::load(game::encounter::Encounters::PATH, &data.species)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load encounters, there won't be any: {}", e);
+                game::encounter::Encounters::default()
+            });
+
+    let mut play = if has_arg("--play") {
+        match game::play::Play::start(
+            &editor.map,
+            state.camera.eye,
+            play_rng(),
+            &play_settings,
+            &data,
+        ) {
+            Ok(play) => Some(play),
+            Err(e) => {
+                eprintln!("Failed to start playing: {}", e);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut last_frame = Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
             ref event,
             window_id,
         } if window_id == window.id() => {
-            if !state.input(event) {
-                if !scene.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                        WindowEvent::KeyboardInput { input, .. } => match input {
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            } => *control_flow = ControlFlow::Exit,
-                            _ => {}
-                        },
-                        _ => {}
-                    }
+            if input.process_event(event) {
+                return;
+            }
+            match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(size) => state.resize(*size),
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    state.resize(**new_inner_size)
                 }
+                _ => {}
             }
         }
         Event::MainEventsCleared => {
-            window.request_redraw();
+            if input.pressed(input::Action::Quit) {
+                *control_flow = ControlFlow::Exit;
+            }
+
+            // Sleep until the window is restored rather than spinning, and don't count
+            // the time spent minimized as game time
+            if state.is_minimized() {
+                *control_flow = ControlFlow::Wait;
+                last_frame = Instant::now();
+            } else {
+                *control_flow = ControlFlow::Poll;
+                window.request_redraw();
+            }
         }
         Event::RedrawRequested(_) => {
-            state.camera.aspect =
-                window.inner_size().width as f32 / window.inner_size().height as f32;
+            let now = Instant::now();
+            let dt = (now - last_frame).as_secs_f32();
+            last_frame = now;
+
+            input.update(dt);
+            if let Some((_, recording)) = &mut recording {
+                recording.record_frame(dt, &input);
+            }
 
-            scene.tick(&mut state);
+            // Both modes work on the same map in memory, so edits can be walked on
+            // straight away without saving
+            if input.pressed(input::Action::TogglePlay) {
+                play = match play.take() {
+                    Some(play) => {
+                        state.camera.eye = play.editor_eye;
+                        None
+                    }
+                    None => match game::play::Play::start(
+                        &editor.map,
+                        state.camera.eye,
+                        play_rng(),
+                        &play_settings,
+                        &data,
+                    ) {
+                        Ok(play) => Some(play),
+                        Err(e) => {
+                            eprintln!("Failed to start playing: {}", e);
+                            None
+                        }
+                    },
+                };
+            }
 
-            state.update();
-            state.render(&window);
+            match &mut play {
+                Some(play) => {
+                    // Battles can't be shown yet, so they're fought on the spot and only
+                    // their result is printed
+                    match play.update(&editor.map, &encounters, &data, &input, dt) {
+                        Ok(Some(battle)) => println!("{}", battle),
+                        Ok(None) => {}
+                        Err(e) => eprintln!("{}", e),
+                    }
+                    state.camera_target = play.camera_target();
+                }
+                None => {
+                    if let Err(e) = editor.update(&input) {
+                        eprintln!("{}", e);
+                    }
+                    state.camera_target = None;
+                }
+            }
+
+            // The camera moves before culling so chunks aren't culled against last frame's view
+            state.update(&input, dt);
+
+            scene.show_passability = play.is_none() && editor.show_passability;
+            let (cursor, world) = match &mut play {
+                Some(play) => (None, Some(&mut play.world)),
+                None => (Some(editor.selected), None),
+            };
+            let stats = scene.tick(&mut state, &editor.map, cursor, world);
+            let mut readout = format!("chunks {}/{}", stats.drawn, stats.total);
+            if play.is_none() {
+                let (x, y) = editor.selected;
+                if let Some(tile) = editor.map.get_tile(x, y) {
+                    readout += &format!("  zone {}", tile.zone);
+                }
+            }
+            state.overlay.text(8.0, 8.0, &readout);
+
+            state.render();
+
+            input.end_frame();
+        }
+        Event::LoopDestroyed => {
+            if let Some((path, recording)) = &recording {
+                if let Err(e) = recording.save(path) {
+                    eprintln!("Failed to save recording: {}", e);
+                }
+            }
         }
         _ => {}
     });
@@ -1,20 +1,37 @@
+pub mod animation;
+pub mod assets;
+pub mod material;
+pub mod overlay;
+pub mod settings;
+pub mod shadow;
 pub mod spritebatch;
 pub mod texture;
 
 use crate::camera::{Camera, CameraController};
+use crate::input::{Action, Input};
+use spritebatch::BlendMode;
 use std::sync::Arc;
 use ultraviolet::{Mat4, Vec3};
-use winit::{event::WindowEvent, window::Window};
+use winit::window::Window;
 
 pub struct State {
     surface: wgpu::Surface,
     pub device: Arc<wgpu::Device>,
-    pub queue: wgpu::Queue,
+    pub queue: Arc<wgpu::Queue>,
     sc_desc: wgpu::SwapChainDescriptor,
     swap_chain: wgpu::SwapChain,
     size: winit::dpi::PhysicalSize<u32>,
 
+    render_pipeline_layout: wgpu::PipelineLayout,
     render_pipeline: wgpu::RenderPipeline,
+    cutout_pipeline: wgpu::RenderPipeline,
+    translucent_pipeline: wgpu::RenderPipeline,
+    blit_pipeline_layout: wgpu::PipelineLayout,
+    blit_pipeline: wgpu::RenderPipeline,
+    /// Text drawn over the frame, queue lines with [`overlay::Overlay::text`] every frame
+    pub overlay: overlay::Overlay,
+
+    pub assets: assets::AssetManager,
 
     #[allow(dead_code)]
     pub camera: Camera,
@@ -25,36 +42,49 @@ pub struct State {
     uniform_buffer: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
 
-    camera_controller: CameraController,
+    pub light: Light,
+    light_buffer: wgpu::Buffer,
 
-    depth_texture: Arc<texture::Texture>,
+    shadow_map: shadow::ShadowMap,
+    materials: material::Materials,
 
-    #[allow(dead_code)]
-    render_texture_depth_texture: Arc<texture::Texture>,
-    #[allow(dead_code)]
-    render_texture: Arc<texture::Texture>,
+    camera_controller: CameraController,
+    /// While set the camera looks at this from its usual angle and ignores the camera keys
+    pub camera_target: Option<Vec3>,
+
+    settings: settings::GraphicsSettings,
+    render_targets: RenderTargets,
 
-    pub spritebatch_buffers: Vec<(
-        Arc<texture::Texture>,
-        spritebatch::VertexBuffer,
-        spritebatch::IndexBuffer,
-    )>,
+    pub spritebatch_buffers: Vec<spritebatch::Batch>,
 }
 
 impl State {
+    /// Radians per second the light turns while rotating it
+    const LIGHT_SPEED: f32 = 1.2;
+    /// How far the eye is from [`State::camera_target`]
+    const FOLLOW_DISTANCE: f32 = 56.0;
+
     // Creating some of the wgpu types requires async code
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(
+        window: &Window,
+        settings: settings::GraphicsSettings,
+    ) -> Result<Self, String> {
         let window_size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let instance = wgpu::Instance::new(settings.backends);
         let surface = unsafe { instance.create_surface(window) };
         let adapater = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::Default,
+                power_preference: settings.power_preference,
                 compatible_surface: Some(&surface),
             })
             .await
-            .unwrap();
+            .ok_or_else(|| {
+                format!(
+                    "No graphics adapter that can draw to the window was found with backends {:?} and power preference {:?}",
+                    settings.backends, settings.power_preference
+                )
+            })?;
 
         let (device, queue) = adapater
             .request_device(
@@ -66,9 +96,13 @@ impl State {
                 None,
             )
             .await
-            .unwrap();
+            .map_err(|e| format!("Failed to open the graphics device: {}", e))?;
 
         let device = Arc::new(device);
+        let queue = Arc::new(queue);
+
+        let mut assets =
+            assets::AssetManager::new(assets::AssetManager::ROOT, device.clone(), queue.clone());
 
         let texture_format = wgpu::TextureFormat::Bgra8UnormSrgb;
 
@@ -77,7 +111,7 @@ impl State {
             format: texture_format,
             width: window_size.width,
             height: window_size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: settings.present_mode,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
@@ -91,11 +125,12 @@ impl State {
             eye,
             direction,
             up: Vec3::unit_y(),
-            // aspect gets overwritten every frame
-            aspect: 1.0 / 1.0,
+            // Kept up to date by resize
+            aspect: aspect_ratio(window_size),
             fov_y: 12.0_f32.to_radians(),
             z_near: 1.0,
             z_far: 500.0,
+            draw_distance: 150.0,
         };
 
         // Uniforms
@@ -110,96 +145,96 @@ impl State {
             usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
         });
 
+        // Light
+        let light = Light::new(Vec3::new(-0.4, -1.0, -0.6), [1.0, 1.0, 1.0], 0.35);
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light_buffer"),
+            contents: bytemuck::cast_slice(&[light]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
         let uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStage::VERTEX,
-                    ty: wgpu::BindingType::UniformBuffer {
-                        dynamic: false,
-                        min_binding_size: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::VERTEX,
+                        ty: wgpu::BindingType::UniformBuffer {
+                            dynamic: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::UniformBuffer {
+                            dynamic: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
                 label: Some("unform_bind_group_layout"),
             });
 
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &uniform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(light_buffer.slice(..)),
+                },
+            ],
             label: Some("uniform_bind_group"),
         });
 
+        let shadow_vs_module = assets.shader("shaders/shadow.vert")?;
+        let shadow_map = shadow::ShadowMap::new(&device, settings.shadows, &shadow_vs_module);
+        let materials = material::Materials::new(&device);
+
         // Render pipeline
-        let vs_module = device.create_shader_module(wgpu::include_spirv!("res/shader.vert.spv"));
-        let fs_module = device.create_shader_module(wgpu::include_spirv!("res/shader.frag.spv"));
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&diffuse_bind_group_layout, &uniform_bind_group_layout],
+                bind_group_layouts: &[
+                    &diffuse_bind_group_layout,
+                    &uniform_bind_group_layout,
+                    &shadow_map.bind_group_layout,
+                    &materials.bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-                clamp_depth: false,
-            }),
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: texture_format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
-                format: texture::Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilStateDescriptor::default(),
-            }),
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint16,
-                vertex_buffers: &[Vertex::descriptor()],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
+        let (render_pipeline, cutout_pipeline, translucent_pipeline) = create_render_pipelines(
+            &device,
+            &render_pipeline_layout,
+            &mut assets,
+            texture_format,
+            settings.msaa_samples,
+        )?;
+
+        let blit_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&diffuse_bind_group_layout],
+            push_constant_ranges: &[],
         });
+        let blit_pipeline =
+            create_blit_pipeline(&device, &blit_pipeline_layout, &mut assets, texture_format)?;
 
-        let camera_controller = CameraController::new(0.2);
+        let overlay = overlay::Overlay::new(&device, &mut assets, texture_format)?;
 
-        let depth_texture = texture::Texture::create_depth_texture(
-            &device,
-            sc_desc.width,
-            sc_desc.height,
-            "depth_texture",
-        );
+        let camera_controller = CameraController::new(0.2);
 
-        let render_texture_depth_texture =
-            texture::Texture::create_depth_texture(&device, 320, 180, "rt_depth_texture");
-        let render_texture =
-            texture::Texture::empty_texture(&device, &queue, 320, 180, "render_Texture");
+        let render_targets = RenderTargets::new(&device, &sc_desc, &settings);
 
-        Self {
+        Ok(Self {
             surface,
             device,
             queue,
@@ -207,7 +242,15 @@ impl State {
             swap_chain,
             size: window_size,
 
+            render_pipeline_layout,
             render_pipeline,
+            cutout_pipeline,
+            translucent_pipeline,
+            blit_pipeline_layout,
+            blit_pipeline,
+            overlay,
+
+            assets,
 
             camera,
 
@@ -215,54 +258,158 @@ impl State {
             uniform_buffer,
             uniform_bind_group,
 
-            camera_controller,
+            light,
+            light_buffer,
 
-            depth_texture,
+            shadow_map,
+            materials,
 
-            render_texture_depth_texture,
-            render_texture,
+            camera_controller,
+            camera_target: None,
+
+            settings,
+            render_targets,
 
             spritebatch_buffers: Vec::new(),
-        }
+        })
     }
 
+    /// Recreates the swap chain and render targets for the new size and updates the
+    /// camera's aspect ratio. A minimized window keeps the old ones until it is restored.
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.size = new_size;
+        if self.is_minimized() {
+            return;
+        }
+
+        self.camera.aspect = aspect_ratio(new_size);
+
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
 
-        self.depth_texture = texture::Texture::create_depth_texture(
-            &self.device,
-            self.sc_desc.width,
-            self.sc_desc.height,
-            "depth_texture",
-        );
+        self.render_targets = RenderTargets::new(&self.device, &self.sc_desc, &self.settings);
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        self.camera_controller.process_events(event)
+    /// A zero sized window can't have a swap chain, nothing is rendered until it grows
+    pub fn is_minimized(&self) -> bool {
+        self.size.width == 0 || self.size.height == 0
     }
 
-    pub fn update(&mut self) {
-        self.camera_controller.update_camera(&mut self.camera);
+    /// `dt` is the seconds of game time since the last update, it drives texture animations
+    /// and turning the light
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        match self.camera_target {
+            Some(target) => {
+                self.camera.eye =
+                    target - self.camera.direction.normalized() * Self::FOLLOW_DISTANCE
+            }
+            None => self
+                .camera_controller
+                .update_camera(&mut self.camera, input),
+        }
         self.uniforms.update_view_proj(&self.camera);
+        self.uniforms.time += dt;
+
+        self.reload_assets();
         self.queue.write_buffer(
             &self.uniform_buffer,
             0,
             bytemuck::cast_slice(&[self.uniforms]),
         );
+
+        if input.held(Action::LightLeft) {
+            self.light.rotate_y(-Self::LIGHT_SPEED * dt);
+        }
+        if input.held(Action::LightRight) {
+            self.light.rotate_y(Self::LIGHT_SPEED * dt);
+        }
+        self.queue
+            .write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&[self.light]));
+
+        if input.pressed(Action::ToggleShadows) {
+            let mut settings = self.shadow_map.settings();
+            settings.enabled = !settings.enabled;
+            self.set_shadow_settings(settings);
+        }
+        self.shadow_map
+            .update(&self.queue, &self.light, &self.camera);
+    }
+
+    /// Rebuilds the pipelines if any of their shaders changed on disk
+    fn reload_assets(&mut self) {
+        let reloaded = self.assets.reload_changed();
+        if !reloaded.iter().any(|path| path.starts_with("shaders")) {
+            return;
+        }
+
+        match create_render_pipelines(
+            &self.device,
+            &self.render_pipeline_layout,
+            &mut self.assets,
+            self.sc_desc.format,
+            self.settings.msaa_samples,
+        ) {
+            Ok((render_pipeline, cutout_pipeline, translucent_pipeline)) => {
+                self.render_pipeline = render_pipeline;
+                self.cutout_pipeline = cutout_pipeline;
+                self.translucent_pipeline = translucent_pipeline;
+            }
+            Err(e) => eprintln!("Failed to rebuild render pipelines: {}", e),
+        }
+
+        match create_blit_pipeline(
+            &self.device,
+            &self.blit_pipeline_layout,
+            &mut self.assets,
+            self.sc_desc.format,
+        ) {
+            Ok(blit_pipeline) => self.blit_pipeline = blit_pipeline,
+            Err(e) => eprintln!("Failed to rebuild blit pipeline: {}", e),
+        }
+
+        if let Err(e) = self
+            .overlay
+            .reload(&self.device, &mut self.assets, self.sc_desc.format)
+        {
+            eprintln!("Failed to rebuild overlay pipeline: {}", e);
+        }
+
+        match self.assets.shader("shaders/shadow.vert") {
+            Ok(vs_module) => self.shadow_map.set_shader(&self.device, &vs_module),
+            Err(e) => eprintln!("Failed to rebuild shadow pipeline: {}", e),
+        }
+    }
+
+    pub fn shadow_settings(&self) -> shadow::ShadowSettings {
+        self.shadow_map.settings()
     }
 
-    pub fn render(&mut self, window: &Window) {
+    pub fn set_shadow_settings(&mut self, settings: shadow::ShadowSettings) {
+        self.shadow_map.set_settings(&self.device, settings);
+    }
+
+    pub fn render(&mut self) {
+        if self.is_minimized() {
+            return;
+        }
+
         let frame = self.swap_chain.get_current_frame();
         let frame = match frame {
-            Result::Err(wgpu::SwapChainError::Outdated) => {
-                self.resize(window.inner_size());
-                self.swap_chain.get_current_frame().unwrap().output
+            Result::Err(wgpu::SwapChainError::Outdated)
+            | Result::Err(wgpu::SwapChainError::Lost) => {
+                self.resize(self.size);
+                match self.swap_chain.get_current_frame() {
+                    Result::Ok(swap_chain) => swap_chain.output,
+                    // Still resizing, try again next frame
+                    Result::Err(_) => return,
+                }
             }
             Result::Ok(swap_chain) => swap_chain.output,
-            _ => panic!("Timeout getting texture"),
+            Result::Err(wgpu::SwapChainError::Timeout) => return,
+            Result::Err(wgpu::SwapChainError::OutOfMemory) => {
+                panic!("Out of memory getting texture")
+            }
         };
 
         let buffer_one = {
@@ -273,16 +420,37 @@ impl State {
                 });
 
             let mut bind_groups = Vec::new();
-            for (texture, _, _) in self.spritebatch_buffers.iter() {
-                let (diffuse_bind_group, _) = texture.create_bind_group(&self.device);
+            for batch in self.spritebatch_buffers.iter() {
+                let (diffuse_bind_group, _) = batch.texture.create_bind_group(&self.device);
                 bind_groups.push(diffuse_bind_group);
             }
 
+            self.shadow_map
+                .render(&mut encoder, &self.spritebatch_buffers);
+
+            self.materials.write(
+                &self.device,
+                &self.queue,
+                self.spritebatch_buffers
+                    .iter()
+                    .map(|batch| batch.texture.animation),
+            );
+
+            // The scene ends up in the scaled target when there is one, multisampled
+            // rendering resolves into it rather than drawing to it directly
+            let output = match &self.render_targets.scaled {
+                Some(scaled) => &scaled.view,
+                None => &frame.view,
+            };
+            let (attachment, resolve_target) = match &self.render_targets.multisampled {
+                Some(multisampled) => (&multisampled.view, Some(output)),
+                None => (output, None),
+            };
+
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    //attachment: &self.render_texture.view,
-                    attachment: &frame.view,
-                    resolve_target: None,
+                    attachment,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
                             r: 0.1,
@@ -294,8 +462,7 @@ impl State {
                     },
                 }],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                    //attachment: &self.render_texture_depth_texture.view,
-                    attachment: &self.depth_texture.view,
+                    attachment: &self.render_targets.depth.view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: true,
@@ -303,111 +470,315 @@ impl State {
                     stencil_ops: None,
                 }),
             });
-            render_pass.set_pipeline(&self.render_pipeline);
-
             render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
-            for ((_, vertex_buffer, index_buffer), bind_group) in
-                self.spritebatch_buffers.iter().zip(bind_groups.iter())
+            render_pass.set_bind_group(2, &self.shadow_map.bind_group, &[]);
+
+            let mut current_mode = None;
+            for (i, (batch, bind_group)) in self
+                .spritebatch_buffers
+                .iter()
+                .zip(bind_groups.iter())
+                .enumerate()
             {
-                let spritebatch::IndexBuffer(index_buffer, num_indices) = index_buffer;
+                if current_mode != Some(batch.mode) {
+                    current_mode = Some(batch.mode);
+                    render_pass.set_pipeline(match batch.mode {
+                        BlendMode::Opaque => &self.render_pipeline,
+                        BlendMode::Cutout => &self.cutout_pipeline,
+                        BlendMode::Translucent => &self.translucent_pipeline,
+                    });
+                }
+
+                let spritebatch::IndexBuffer(index_buffer, num_indices) = &batch.index_buffer;
                 render_pass.set_bind_group(0, bind_group, &[]);
-                render_pass.set_vertex_buffer(0, vertex_buffer.0.slice(..));
+                render_pass.set_bind_group(
+                    3,
+                    &self.materials.bind_group,
+                    &[material::Materials::offset(i)],
+                );
+                render_pass.set_vertex_buffer(0, batch.vertex_buffer.0.slice(..));
                 render_pass.set_index_buffer(index_buffer.slice(..));
                 render_pass.draw_indexed(0..*num_indices, 0, 0..1);
             }
             drop(render_pass);
 
+            if let Some(scaled) = &self.render_targets.scaled {
+                let (source_bind_group, _) = scaled.create_bind_group(&self.device);
+
+                let mut blit_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: &frame.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                blit_pass.set_pipeline(&self.blit_pipeline);
+                blit_pass.set_bind_group(0, &source_bind_group, &[]);
+                blit_pass.draw(0..3, 0..1);
+            }
+
+            self.overlay.render(
+                &self.device,
+                &mut encoder,
+                &frame.view,
+                (self.sc_desc.width, self.sc_desc.height),
+                &mut self.assets,
+            );
+
             encoder.finish()
         };
 
         self.queue.submit(std::iter::once(buffer_one));
-        /*let buffer_two = {
-            #[rustfmt::skip]
-            const VERTICES: &[Vertex] = &[
-                Vertex { position: [-1., 1., 0.], tex_coords: [0., 0.], }, // A
-                Vertex { position: [-1., -1., 0.], tex_coords: [0., 1.], }, // B
-                Vertex { position: [1., -1., 0.], tex_coords: [1., 1.], }, // C
-                Vertex { position: [1., 1., 0.], tex_coords: [1., 0.], }, // D
-            ];
-
-            #[rustfmt::skip]
-            const INDICES: &[u16] = &[
-                0, 1, 3,
-                1, 2, 3,
-            ];
+    }
+}
 
-            let mut encoder = self
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Render Encoder"),
-                });
+/// Everything the scene is drawn into besides the swap chain frame
+struct RenderTargets {
+    depth: Arc<texture::Texture>,
+    /// Resolved into `scaled` or the frame, only used with more than one MSAA sample
+    multisampled: Option<Arc<texture::Texture>>,
+    /// Stretched over the frame afterwards, only used with a resolution scale other than 1
+    scaled: Option<Arc<texture::Texture>>,
+}
 
-            let (bind_group, _) = self.render_texture.create_bind_group(&self.device);
+impl RenderTargets {
+    fn new(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        settings: &settings::GraphicsSettings,
+    ) -> Self {
+        let (width, height) =
+            settings.scaled_size(winit::dpi::PhysicalSize::new(sc_desc.width, sc_desc.height));
 
-            // Create buffers
-            use wgpu::util::DeviceExt;
-            let vertex_buffer = self
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Vertex Buffer"),
-                    contents: bytemuck::cast_slice(&VERTICES),
-                    usage: wgpu::BufferUsage::VERTEX,
-                });
+        let depth = texture::Texture::create_depth_texture(
+            device,
+            width,
+            height,
+            settings.msaa_samples,
+            "depth_texture",
+        );
 
-            let index_buffer = self
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Index Buffer"),
-                    #[rustfmt::skip]
-                    contents: bytemuck::cast_slice(&INDICES),
-                    usage: wgpu::BufferUsage::INDEX,
-                });
+        let multisampled = if settings.msaa_samples > 1 {
+            Some(texture::Texture::create_render_target(
+                device,
+                width,
+                height,
+                sc_desc.format,
+                settings.msaa_samples,
+                "multisampled_texture",
+            ))
+        } else {
+            None
+        };
 
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.5,
-                            g: 0.5,
-                            b: 0.5,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                    attachment: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
+        let scaled = if (width, height) != (sc_desc.width, sc_desc.height) {
+            Some(texture::Texture::create_render_target(
+                device,
+                width,
+                height,
+                sc_desc.format,
+                1,
+                "scaled_texture",
+            ))
+        } else {
+            None
+        };
 
-            let uniform = Uniforms {
-                view: Mat4::identity(),
-                ortho_proj: Mat4::identity(),
-            };
+        Self {
+            depth,
+            multisampled,
+            scaled,
+        }
+    }
+}
 
-            self.queue
-                .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+/// The opaque, cutout and translucent pipelines
+fn create_render_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    assets: &mut assets::AssetManager,
+    texture_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> Result<
+    (
+        wgpu::RenderPipeline,
+        wgpu::RenderPipeline,
+        wgpu::RenderPipeline,
+    ),
+    String,
+> {
+    let vs_module = assets.shader("shaders/shader.vert")?;
+    let fs_module = assets.shader("shaders/shader.frag")?;
+    let cutout_fs_module = assets.shader("shaders/shader.alpha_cutout.frag")?;
+
+    Ok((
+        create_render_pipeline(
+            device,
+            layout,
+            &vs_module,
+            &fs_module,
+            texture_format,
+            sample_count,
+            BlendMode::Opaque,
+        ),
+        create_render_pipeline(
+            device,
+            layout,
+            &vs_module,
+            &cutout_fs_module,
+            texture_format,
+            sample_count,
+            BlendMode::Cutout,
+        ),
+        create_render_pipeline(
+            device,
+            layout,
+            &vs_module,
+            &fs_module,
+            texture_format,
+            sample_count,
+            BlendMode::Translucent,
+        ),
+    ))
+}
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &bind_group, &[]);
-            render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..));
-            render_pass.draw_indexed(0..6, 0, 0..1);
-            drop(render_pass);
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vs_module: &wgpu::ShaderModule,
+    fs_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+    sample_count: u32,
+    mode: BlendMode,
+) -> wgpu::RenderPipeline {
+    let (label, cull_mode, color_blend, alpha_blend, depth_write_enabled) = match mode {
+        BlendMode::Opaque => (
+            "Render Pipeline",
+            wgpu::CullMode::Back,
+            wgpu::BlendDescriptor::REPLACE,
+            wgpu::BlendDescriptor::REPLACE,
+            true,
+        ),
+        // Foliage and fences are usually single quads seen from both sides
+        BlendMode::Cutout => (
+            "Cutout Render Pipeline",
+            wgpu::CullMode::None,
+            wgpu::BlendDescriptor::REPLACE,
+            wgpu::BlendDescriptor::REPLACE,
+            true,
+        ),
+        // Translucent draws are sorted rather than depth tested against each other
+        BlendMode::Translucent => (
+            "Translucent Render Pipeline",
+            wgpu::CullMode::None,
+            wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            false,
+        ),
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: vs_module,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: fs_module,
+            entry_point: "main",
+        }),
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+            clamp_depth: false,
+        }),
+        color_states: &[wgpu::ColorStateDescriptor {
+            format: texture_format,
+            color_blend,
+            alpha_blend,
+            write_mask: wgpu::ColorWrite::ALL,
+        }],
+        primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+        depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilStateDescriptor::default(),
+        }),
+        vertex_state: wgpu::VertexStateDescriptor {
+            index_format: wgpu::IndexFormat::Uint16,
+            vertex_buffers: &[Vertex::descriptor()],
+        },
+        sample_count,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
+}
 
-            encoder.finish()
-        };
+/// Copies a render target over the whole frame, scaling it to fit
+fn create_blit_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    assets: &mut assets::AssetManager,
+    texture_format: wgpu::TextureFormat,
+) -> Result<wgpu::RenderPipeline, String> {
+    let vs_module = assets.shader("shaders/blit.vert")?;
+    let fs_module = assets.shader("shaders/blit.frag")?;
+
+    Ok(
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: Some(layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                ..Default::default()
+            }),
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: texture_format,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        }),
+    )
+}
 
-        self.queue.submit(std::iter::once(buffer_two));*/
-    }
+fn aspect_ratio(size: winit::dpi::PhysicalSize<u32>) -> f32 {
+    size.width as f32 / size.height.max(1) as f32
 }
 
 unsafe impl bytemuck::Pod for Uniforms {}
@@ -418,6 +789,9 @@ unsafe impl bytemuck::Zeroable for Uniforms {}
 struct Uniforms {
     view: Mat4,
     ortho_proj: Mat4,
+    /// Seconds since the renderer started, for texture animations
+    time: f32,
+    _padding: [f32; 3],
 }
 
 impl Uniforms {
@@ -425,6 +799,8 @@ impl Uniforms {
         Self {
             view: Mat4::identity(),
             ortho_proj: Mat4::identity(),
+            time: 0.0,
+            _padding: [0.0; 3],
         }
     }
 
@@ -435,6 +811,38 @@ impl Uniforms {
     }
 }
 
+unsafe impl bytemuck::Pod for Light {}
+unsafe impl bytemuck::Zeroable for Light {}
+
+/// A single directional light, laid out to match the std140 `Light` block in the shaders
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Light {
+    /// The direction the light travels in, doesn't need to be normalized
+    pub direction: [f32; 3],
+    _padding: f32,
+    pub color: [f32; 3],
+    /// How much of `color` reaches faces that point away from the light
+    pub ambient: f32,
+}
+
+impl Light {
+    pub fn new(direction: Vec3, color: [f32; 3], ambient: f32) -> Self {
+        Self {
+            direction: direction.into(),
+            _padding: 0.0,
+            color,
+            ambient,
+        }
+    }
+
+    /// Spins the light around the vertical axis
+    pub fn rotate_y(&mut self, radians: f32) {
+        let direction = ultraviolet::Rotor3::from_rotation_xz(radians) * Vec3::from(self.direction);
+        self.direction = direction.into();
+    }
+}
+
 unsafe impl bytemuck::Pod for Vertex {}
 unsafe impl bytemuck::Zeroable for Vertex {}
 
@@ -443,6 +851,7 @@ unsafe impl bytemuck::Zeroable for Vertex {}
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
 }
 
 impl Vertex {
@@ -461,6 +870,11 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float2,
                 },
+                wgpu::VertexAttributeDescriptor {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float3,
+                },
             ],
         }
     }
@@ -0,0 +1,259 @@
+//! Sprite sheet animations, loaded from RON files such as
+//!
+//! ```ron
+//! (
+//!     clips: [
+//!         (name: "idle", frames: [(x: 0, y: 0, width: 16, height: 24, duration: 0.5)]),
+//!         (
+//!             name: "walk_down",
+//!             mode: "Loop",
+//!             frames: [
+//!                 (x: 16, y: 0, width: 16, height: 24, duration: 0.15),
+//!                 (x: 32, y: 0, width: 16, height: 24, duration: 0.15),
+//!             ],
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! Frames are rects in pixels of the sheet's texture, `mode` is one of `Once`, `Loop`
+//! or `PingPong` and defaults to `Loop`.
+
+use super::spritebatch::UvRect;
+use super::texture::Texture;
+use nanoserde::DeRon;
+
+/// A rect of the sprite sheet in pixels and how many seconds it is shown for
+#[derive(DeRon, Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub duration: f32,
+}
+
+impl Frame {
+    pub fn uv(&self, texture: &Texture) -> UvRect {
+        UvRect::from_pixels(texture, self.x, self.y, self.width, self.height)
+    }
+}
+
+/// What a clip does once it reaches its last frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Stops on the last frame
+    Once,
+    /// Starts again from the first frame
+    Loop,
+    /// Plays backwards to the first frame, then forwards again
+    PingPong,
+}
+
+impl LoopMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Once" => Some(LoopMode::Once),
+            "Loop" => Some(LoopMode::Loop),
+            "PingPong" => Some(LoopMode::PingPong),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clip {
+    pub name: String,
+    pub mode: LoopMode,
+    pub frames: Vec<Frame>,
+}
+
+impl Clip {
+    /// Seconds to play every frame once
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    /// The frame shown `time` seconds after the clip started
+    pub fn frame_at(&self, time: f32) -> &Frame {
+        let duration = self.duration();
+        let time = match self.mode {
+            LoopMode::Once => time.min(duration),
+            LoopMode::Loop => time % duration,
+            LoopMode::PingPong => {
+                let time = time % (duration * 2.0);
+                if time > duration {
+                    duration * 2.0 - time
+                } else {
+                    time
+                }
+            }
+        };
+
+        let mut start = 0.0;
+        for frame in self.frames.iter() {
+            start += frame.duration;
+            if time < start {
+                return frame;
+            }
+        }
+        self.frames.last().unwrap()
+    }
+
+    /// True once a [`LoopMode::Once`] clip has shown its last frame for its full duration
+    pub fn finished(&self, time: f32) -> bool {
+        self.mode == LoopMode::Once && time >= self.duration()
+    }
+}
+
+#[derive(DeRon)]
+struct ClipConfig {
+    name: String,
+    mode: Option<String>,
+    frames: Vec<Frame>,
+}
+
+#[derive(DeRon)]
+struct SpriteSheetConfig {
+    clips: Vec<ClipConfig>,
+}
+
+/// The named clips of one sprite sheet texture
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    pub clips: Vec<Clip>,
+}
+
+impl SpriteSheet {
+    pub fn from_ron(src: &str) -> Result<Self, String> {
+        let config = SpriteSheetConfig::deserialize_ron(src).map_err(|e| format!("{:?}", e))?;
+
+        let mut clips: Vec<Clip> = Vec::with_capacity(config.clips.len());
+        for clip in config.clips {
+            if clips.iter().any(|other| other.name == clip.name) {
+                return Err(format!("clip `{}` is defined twice", clip.name));
+            }
+            if clip.frames.is_empty() {
+                return Err(format!("clip `{}` has no frames", clip.name));
+            }
+            if clip.frames.iter().any(|frame| frame.duration <= 0.0) {
+                return Err(format!(
+                    "clip `{}` has a frame without a positive duration",
+                    clip.name
+                ));
+            }
+
+            let mode = match &clip.mode {
+                Some(name) => LoopMode::from_name(name)
+                    .ok_or_else(|| format!("clip `{}`: unknown mode `{}`", clip.name, name))?,
+                None => LoopMode::Loop,
+            };
+
+            clips.push(Clip {
+                name: clip.name,
+                mode,
+                frames: clip.frames,
+            });
+        }
+
+        Ok(Self { clips })
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+}
+
+/// Playback state of one sprite, advanced with game time by [`Animation::update`]
+#[derive(Clone, Debug)]
+pub struct Animation {
+    clip: String,
+    /// Seconds since the current clip started
+    time: f32,
+    /// Multiplies the time passed to [`Animation::update`]
+    pub speed: f32,
+}
+
+impl Animation {
+    pub fn new(clip: &str) -> Self {
+        Self {
+            clip: clip.to_string(),
+            time: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    /// Switches to `clip` from its first frame, keeps playing if it is already the current clip
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip {
+            self.clip = clip.to_string();
+            self.time = 0.0;
+        }
+    }
+
+    /// Starts the current clip again from its first frame
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt * self.speed;
+    }
+
+    /// The frame to draw now, `None` if the sheet has no clip with the current name
+    pub fn frame<'a>(&self, sheet: &'a SpriteSheet) -> Option<&'a Frame> {
+        sheet.clip(&self.clip).map(|clip| clip.frame_at(self.time))
+    }
+
+    /// The current frame as texture coordinates for [`super::spritebatch::Sprite::uv`]
+    pub fn uv(&self, sheet: &SpriteSheet, texture: &Texture) -> Option<UvRect> {
+        self.frame(sheet).map(|frame| frame.uv(texture))
+    }
+
+    pub fn finished(&self, sheet: &SpriteSheet) -> bool {
+        sheet
+            .clip(&self.clip)
+            .map_or(false, |clip| clip.finished(self.time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_cycle_loops_through_its_frames() {
+        let sheet = SpriteSheet::from_ron(
+            r#"(clips: [(name: "walk", frames: [
+                (x: 0, y: 0, width: 16, height: 24, duration: 0.25),
+                (x: 16, y: 0, width: 16, height: 24, duration: 0.25),
+            ])])"#,
+        )
+        .unwrap();
+
+        let mut animation = Animation::new("walk");
+        let mut shown = Vec::new();
+        for _ in 0..4 {
+            shown.push(animation.frame(&sheet).unwrap().x);
+            animation.update(0.25);
+        }
+        assert_eq!(shown, [0, 16, 0, 16]);
+        assert!(!animation.finished(&sheet));
+    }
+
+    #[test]
+    fn player_sheet_has_every_clip_the_world_plays() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/textures/player.ron");
+        let sheet = SpriteSheet::from_ron(&std::fs::read_to_string(path).unwrap()).unwrap();
+        for action in ["idle", "walk"].iter() {
+            for facing in crate::game::world::Facing::ALL.iter() {
+                let name = format!("{}_{}", action, facing.name());
+                assert!(sheet.clip(&name).is_some(), "no clip `{}`", name);
+            }
+        }
+    }
+}
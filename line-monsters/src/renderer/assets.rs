@@ -0,0 +1,418 @@
+//! Textures, shaders, sprite sheets and meshes loaded at runtime from the assets directory.
+//!
+//! Every asset is loaded once per path and handed out as a shared handle. In debug
+//! builds [`AssetManager::reload_changed`] polls the files for changes and replaces
+//! the cached handles, anything holding on to a handle should fetch it again when
+//! [`AssetManager::generation`] changes.
+//!
+//! Shaders are the exception, as the game only loads the SPIR-V that `build.rs` compiles
+//! the GLSL into. Edits to a shader's GLSL are picked up once `cargo build` has run again,
+//! the running game doesn't have to be restarted.
+
+use super::animation::SpriteSheet;
+use super::texture::{Texture, TextureOptions};
+use super::Vertex;
+use nanoserde::DeRon;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use wgpu::{Device, Queue};
+
+/// Vertices and indices of a model, loaded from a RON file such as
+///
+/// ```ron
+/// (
+///     vertices: [
+///         (position: (-0.5, -0.5, -0.5), tex_coords: (0.0, 0.0), normal: (0.0, 1.0, 0.0)),
+///         ...
+///     ],
+///     indices: [0, 1, 2],
+/// )
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+}
+
+#[derive(DeRon)]
+struct MeshVertex {
+    position: [f32; 3],
+    tex_coords: [f32; 2],
+    normal: [f32; 3],
+}
+
+#[derive(DeRon)]
+struct MeshConfig {
+    vertices: Vec<MeshVertex>,
+    indices: Vec<u16>,
+}
+
+impl Mesh {
+    /// Every index has to point at one of the vertices
+    pub fn from_ron(src: &str) -> Result<Self, String> {
+        let config = MeshConfig::deserialize_ron(src).map_err(|e| format!("{:?}", e))?;
+
+        if let Some(index) = config
+            .indices
+            .iter()
+            .find(|index| **index as usize >= config.vertices.len())
+        {
+            return Err(format!(
+                "index {} is out of range of {} vertices",
+                index,
+                config.vertices.len()
+            ));
+        }
+        if config.indices.len() % 3 != 0 {
+            return Err(format!(
+                "{} indices don't make whole triangles",
+                config.indices.len()
+            ));
+        }
+
+        let vertices = config
+            .vertices
+            .iter()
+            .map(|vertex| Vertex {
+                position: vertex.position,
+                tex_coords: vertex.tex_coords,
+                normal: vertex.normal,
+            })
+            .collect();
+        Ok(Self {
+            vertices,
+            indices: config.indices,
+        })
+    }
+}
+
+/// A cached handle and when its file was last modified
+struct Asset<T> {
+    handle: T,
+    /// Only checked by hot reloading in debug builds
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    modified: Option<SystemTime>,
+}
+
+pub struct AssetManager {
+    root: PathBuf,
+
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+
+    /// Keyed by the path relative to `root`
+    textures: HashMap<PathBuf, (Asset<Arc<Texture>>, TextureOptions)>,
+    shaders: HashMap<PathBuf, Asset<Arc<wgpu::ShaderModule>>>,
+    sprite_sheets: HashMap<PathBuf, Asset<Arc<SpriteSheet>>>,
+    meshes: HashMap<PathBuf, Asset<Arc<Mesh>>>,
+
+    generation: u64,
+    #[cfg(debug_assertions)]
+    last_poll: std::time::Instant,
+}
+
+impl AssetManager {
+    pub const ROOT: &'static str = "assets";
+
+    /// Seconds between checking the files for changes
+    #[cfg(debug_assertions)]
+    const POLL_INTERVAL: f32 = 0.5;
+
+    pub fn new(root: impl Into<PathBuf>, device: Arc<Device>, queue: Arc<Queue>) -> Self {
+        Self {
+            root: root.into(),
+
+            device,
+            queue,
+
+            textures: HashMap::new(),
+            shaders: HashMap::new(),
+            sprite_sheets: HashMap::new(),
+            meshes: HashMap::new(),
+
+            generation: 0,
+            #[cfg(debug_assertions)]
+            last_poll: std::time::Instant::now(),
+        }
+    }
+
+    /// Bumped every time a cached asset is replaced by a reload
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn read(&self, path: &Path) -> Result<(Vec<u8>, Option<SystemTime>), String> {
+        let full_path = self.root.join(path);
+        let bytes =
+            std::fs::read(&full_path).map_err(|e| format!("{}: {}", full_path.display(), e))?;
+        Ok((bytes, modified(&full_path)))
+    }
+
+    pub fn texture(&mut self, path: impl AsRef<Path>) -> Result<Arc<Texture>, String> {
+        self.texture_with(path, TextureOptions::default())
+    }
+
+    /// The options are only used the first time a path is loaded, later calls
+    /// get the texture that is already cached
+    pub fn texture_with(
+        &mut self,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Arc<Texture>, String> {
+        let path = path.as_ref();
+        if let Some((asset, _)) = self.textures.get(path) {
+            return Ok(asset.handle.clone());
+        }
+
+        let asset = self.load_texture(path, options)?;
+        let texture = asset.handle.clone();
+        self.textures.insert(path.to_path_buf(), (asset, options));
+        Ok(texture)
+    }
+
+    fn load_texture(
+        &self,
+        path: &Path,
+        options: TextureOptions,
+    ) -> Result<Asset<Arc<Texture>>, String> {
+        let (bytes, modified) = self.read(path)?;
+        let label = self.root.join(path).to_string_lossy().into_owned();
+        let handle = Texture::from_bytes_with(&self.device, &self.queue, &bytes, &label, options)?;
+        Ok(Asset { handle, modified })
+    }
+
+    /// Loads the SPIR-V that `build.rs` compiles `path` into, `path` is the GLSL source
+    /// such as `"shaders/shader.vert"`. Reloads watch the SPIR-V rather than the source.
+    pub fn shader(&mut self, path: impl AsRef<Path>) -> Result<Arc<wgpu::ShaderModule>, String> {
+        let path = path.as_ref();
+        if let Some(asset) = self.shaders.get(path) {
+            return Ok(asset.handle.clone());
+        }
+
+        let asset = self.load_shader(path)?;
+        let shader = asset.handle.clone();
+        self.shaders.insert(path.to_path_buf(), asset);
+        Ok(shader)
+    }
+
+    fn load_shader(&self, path: &Path) -> Result<Asset<Arc<wgpu::ShaderModule>>, String> {
+        let spv_path = spv_path(path);
+        let (bytes, modified) = self.read(&spv_path)?;
+        // make_spirv panics on anything that isn't SPIR-V
+        if bytes.len() % 4 != 0 || bytes.len() < 4 || bytes[..4] != [0x03, 0x02, 0x23, 0x07] {
+            return Err(format!(
+                "{}: not a SPIR-V module",
+                self.root.join(&spv_path).display()
+            ));
+        }
+
+        let handle = Arc::new(
+            self.device
+                .create_shader_module(wgpu::util::make_spirv(&bytes)),
+        );
+        Ok(Asset { handle, modified })
+    }
+
+    /// Clips of a sprite sheet, see [`SpriteSheet::from_ron`] for the format
+    pub fn sprite_sheet(&mut self, path: impl AsRef<Path>) -> Result<Arc<SpriteSheet>, String> {
+        let path = path.as_ref();
+        if let Some(asset) = self.sprite_sheets.get(path) {
+            return Ok(asset.handle.clone());
+        }
+
+        let asset = self.load_sprite_sheet(path)?;
+        let sheet = asset.handle.clone();
+        self.sprite_sheets.insert(path.to_path_buf(), asset);
+        Ok(sheet)
+    }
+
+    fn load_sprite_sheet(&self, path: &Path) -> Result<Asset<Arc<SpriteSheet>>, String> {
+        let (bytes, modified) = self.read(path)?;
+        let full_path = self.root.join(path);
+
+        let src =
+            String::from_utf8(bytes).map_err(|e| format!("{}: {}", full_path.display(), e))?;
+        let sheet =
+            SpriteSheet::from_ron(&src).map_err(|e| format!("{}: {}", full_path.display(), e))?;
+        Ok(Asset {
+            handle: Arc::new(sheet),
+            modified,
+        })
+    }
+
+    /// A model, see [`Mesh`] for the format
+    pub fn mesh(&mut self, path: impl AsRef<Path>) -> Result<Arc<Mesh>, String> {
+        let path = path.as_ref();
+        if let Some(asset) = self.meshes.get(path) {
+            return Ok(asset.handle.clone());
+        }
+
+        let asset = self.load_mesh(path)?;
+        let mesh = asset.handle.clone();
+        self.meshes.insert(path.to_path_buf(), asset);
+        Ok(mesh)
+    }
+
+    fn load_mesh(&self, path: &Path) -> Result<Asset<Arc<Mesh>>, String> {
+        let (bytes, modified) = self.read(path)?;
+        let full_path = self.root.join(path);
+
+        let src =
+            String::from_utf8(bytes).map_err(|e| format!("{}: {}", full_path.display(), e))?;
+        let mesh = Mesh::from_ron(&src).map_err(|e| format!("{}: {}", full_path.display(), e))?;
+        Ok(Asset {
+            handle: Arc::new(mesh),
+            modified,
+        })
+    }
+
+    /// Reloads every cached asset whose file changed since it was loaded and returns
+    /// their paths. A failed reload is reported and keeps the old asset. Does nothing
+    /// in release builds.
+    #[cfg(debug_assertions)]
+    pub fn reload_changed(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed().as_secs_f32() < Self::POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = std::time::Instant::now();
+
+        let mut reloaded = Vec::new();
+
+        let changed: Vec<_> = self
+            .textures
+            .iter()
+            .filter(|(path, (asset, _))| self.changed(path, asset.modified))
+            .map(|(path, (_, options))| (path.clone(), *options))
+            .collect();
+        for (path, options) in changed {
+            match self.load_texture(&path, options) {
+                Ok(asset) => {
+                    self.textures.insert(path.clone(), (asset, options));
+                    reloaded.push(path);
+                }
+                Err(e) => self.keep_old(&path, e),
+            }
+        }
+
+        let changed: Vec<_> = self
+            .shaders
+            .iter()
+            .filter(|(path, asset)| self.changed(&spv_path(path), asset.modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in changed {
+            match self.load_shader(&path) {
+                Ok(asset) => {
+                    self.shaders.insert(path.clone(), asset);
+                    reloaded.push(path);
+                }
+                Err(e) => self.keep_old(&path, e),
+            }
+        }
+
+        let changed: Vec<_> = self
+            .sprite_sheets
+            .iter()
+            .filter(|(path, asset)| self.changed(path, asset.modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in changed {
+            match self.load_sprite_sheet(&path) {
+                Ok(asset) => {
+                    self.sprite_sheets.insert(path.clone(), asset);
+                    reloaded.push(path);
+                }
+                Err(e) => self.keep_old(&path, e),
+            }
+        }
+
+        let changed: Vec<_> = self
+            .meshes
+            .iter()
+            .filter(|(path, asset)| self.changed(path, asset.modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in changed {
+            match self.load_mesh(&path) {
+                Ok(asset) => {
+                    self.meshes.insert(path.clone(), asset);
+                    reloaded.push(path);
+                }
+                Err(e) => self.keep_old(&path, e),
+            }
+        }
+
+        if !reloaded.is_empty() {
+            self.generation += 1;
+        }
+        reloaded
+    }
+
+    #[cfg(not(debug_assertions))]
+    pub fn reload_changed(&mut self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    #[cfg(debug_assertions)]
+    fn changed(&self, path: &Path, loaded: Option<SystemTime>) -> bool {
+        let modified = modified(&self.root.join(path));
+        modified.is_some() && modified != loaded
+    }
+
+    /// Stops a broken file from being reported again until it changes
+    #[cfg(debug_assertions)]
+    fn keep_old(&mut self, path: &Path, error: String) {
+        eprintln!("Failed to reload {}: {}", path.display(), error);
+
+        if let Some((asset, _)) = self.textures.get_mut(path) {
+            asset.modified = modified(&self.root.join(path));
+        } else if let Some(asset) = self.shaders.get_mut(path) {
+            asset.modified = modified(&self.root.join(spv_path(path)));
+        } else if let Some(asset) = self.sprite_sheets.get_mut(path) {
+            asset.modified = modified(&self.root.join(path));
+        } else if let Some(asset) = self.meshes.get_mut(path) {
+            asset.modified = modified(&self.root.join(path));
+        }
+    }
+}
+
+/// Where `build.rs` writes the compiled shader
+fn spv_path(path: &Path) -> PathBuf {
+    let mut spv_path = path.as_os_str().to_owned();
+    spv_path.push(".spv");
+    PathBuf::from(spv_path)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        let error = Mesh::from_ron(
+            "(vertices: [(position: (0.0, 0.0, 0.0), tex_coords: (0.0, 0.0), normal: (0.0, 1.0, 0.0))], indices: [0, 0, 1])",
+        )
+        .unwrap_err();
+        assert_eq!(error, "index 1 is out of range of 1 vertices");
+    }
+
+    #[test]
+    fn every_model_loads() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/models");
+        for name in ["wall", "floor", "corner", "inner-corner"].iter() {
+            let path = format!("{}/{}.ron", dir, name);
+            let mesh = Mesh::from_ron(&std::fs::read_to_string(&path).unwrap())
+                .map_err(|error| format!("{}: {}", path, error))
+                .unwrap();
+            assert!(!mesh.indices.is_empty(), "{}", path);
+        }
+    }
+}
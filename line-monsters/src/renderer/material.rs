@@ -0,0 +1,157 @@
+use wgpu::Device;
+
+/// How a texture's UVs move over time. Evaluated in the vertex shader from the time in
+/// `Uniforms`, so animated tiles don't need their vertices rebuilt.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextureAnimation {
+    Static,
+    /// The texture is a horizontal strip of `count` equally wide frames, each shown for
+    /// `duration` seconds
+    Frames {
+        count: u32,
+        duration: f32,
+    },
+    /// Textures per second the UVs slide by, the texture wraps around
+    Scroll {
+        speed: [f32; 2],
+    },
+}
+
+unsafe impl bytemuck::Pod for MaterialUniforms {}
+unsafe impl bytemuck::Zeroable for MaterialUniforms {}
+
+/// Laid out to match the std140 `Material` block in the shaders
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct MaterialUniforms {
+    frame_count: f32,
+    frame_duration: f32,
+    scroll: [f32; 2],
+}
+
+impl From<TextureAnimation> for MaterialUniforms {
+    fn from(animation: TextureAnimation) -> Self {
+        match animation {
+            TextureAnimation::Static => Self {
+                frame_count: 1.0,
+                frame_duration: 1.0,
+                scroll: [0.0, 0.0],
+            },
+            TextureAnimation::Frames { count, duration } => Self {
+                frame_count: count.max(1) as f32,
+                frame_duration: duration,
+                scroll: [0.0, 0.0],
+            },
+            TextureAnimation::Scroll { speed } => Self {
+                frame_count: 1.0,
+                frame_duration: 1.0,
+                scroll: speed,
+            },
+        }
+    }
+}
+
+/// One [`TextureAnimation`] per batch in a single uniform buffer, each batch binds its
+/// own entry through a dynamic offset
+pub struct Materials {
+    buffer: wgpu::Buffer,
+    /// Entries the buffer has room for
+    capacity: usize,
+
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Materials {
+    /// Dynamic offsets have to be aligned to this
+    const STRIDE: wgpu::BufferAddress = wgpu::BIND_BUFFER_ALIGNMENT;
+
+    pub fn new(device: &Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<MaterialUniforms>() as _,
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("material_bind_group_layout"),
+        });
+
+        let capacity = 16;
+        let (buffer, bind_group) = Self::create_buffer(device, &bind_group_layout, capacity);
+
+        Self {
+            buffer,
+            capacity,
+
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    fn create_buffer(
+        device: &Device,
+        layout: &wgpu::BindGroupLayout,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("material_buffer"),
+            size: Self::STRIDE * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer.slice(..std::mem::size_of::<MaterialUniforms>() as wgpu::BufferAddress),
+                ),
+            }],
+            label: Some("material_bind_group"),
+        });
+
+        (buffer, bind_group)
+    }
+
+    /// Uploads one entry per animation, growing the buffer if there are more than fit
+    pub fn write(
+        &mut self,
+        device: &Device,
+        queue: &wgpu::Queue,
+        animations: impl ExactSizeIterator<Item = TextureAnimation>,
+    ) {
+        let count = animations.len();
+        if count == 0 {
+            return;
+        }
+
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            let (buffer, bind_group) =
+                Self::create_buffer(device, &self.bind_group_layout, self.capacity);
+            self.buffer = buffer;
+            self.bind_group = bind_group;
+        }
+
+        let mut bytes = vec![0; Self::STRIDE as usize * count];
+        for (i, animation) in animations.enumerate() {
+            let uniforms = MaterialUniforms::from(animation);
+            let start = i * Self::STRIDE as usize;
+            let end = start + std::mem::size_of::<MaterialUniforms>();
+            bytes[start..end].copy_from_slice(bytemuck::bytes_of(&uniforms));
+        }
+        queue.write_buffer(&self.buffer, 0, &bytes);
+    }
+
+    /// The dynamic offset of the entry written for the `index`th animation
+    pub fn offset(index: usize) -> wgpu::DynamicOffset {
+        (index as wgpu::BufferAddress * Self::STRIDE) as wgpu::DynamicOffset
+    }
+}
@@ -0,0 +1,243 @@
+//! Text drawn over the finished frame in screen space, for readouts that have to stay
+//! visible when the window title doesn't, such as in fullscreen
+
+use super::assets::AssetManager;
+use super::texture::{Texture, TextureOptions};
+use super::Vertex;
+use std::sync::Arc;
+use wgpu::Device;
+
+pub struct Overlay {
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+
+    /// Lines queued by [`Overlay::text`] since the last [`Overlay::render`], with the
+    /// pixel position of their top left corner
+    lines: Vec<(f32, f32, String)>,
+}
+
+impl Overlay {
+    const FONT_TEXTURE: &'static str = "textures/font.png";
+    /// The characters in the font texture from left to right, anything else is drawn as
+    /// a space. Followed by one solid cell that the backdrop behind each line uses.
+    const CHARACTERS: &'static str = " 0123456789/:-ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    /// Pixels each character takes up in the font texture, including the gap after it
+    const CELL_WIDTH: u32 = 4;
+    const CELL_HEIGHT: u32 = 6;
+    /// Screen pixels per font texture pixel
+    const SCALE: f32 = 3.0;
+
+    pub fn new(
+        device: &Device,
+        assets: &mut AssetManager,
+        texture_format: wgpu::TextureFormat,
+    ) -> Result<Self, String> {
+        let diffuse_bind_group_layout = Texture::create_bind_group_layout(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&diffuse_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(device, &pipeline_layout, assets, texture_format)?;
+
+        // Loaded up front so a missing font is reported at startup
+        font(assets)?;
+
+        Ok(Self {
+            pipeline_layout,
+            pipeline,
+            lines: Vec::new(),
+        })
+    }
+
+    /// Rebuilds the pipeline after its shaders changed on disk
+    pub fn reload(
+        &mut self,
+        device: &Device,
+        assets: &mut AssetManager,
+        texture_format: wgpu::TextureFormat,
+    ) -> Result<(), String> {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, assets, texture_format)?;
+        Ok(())
+    }
+
+    /// Queues a line of text for the next frame only, `x` and `y` are in pixels from the
+    /// top left of the window. Letters are drawn in upper case.
+    pub fn text(&mut self, x: f32, y: f32, text: &str) {
+        self.lines.push((x, y, text.to_uppercase()));
+    }
+
+    /// Draws the queued lines over `view`, which is `width` by `height` pixels
+    pub fn render(
+        &mut self,
+        device: &Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        (width, height): (u32, u32),
+        assets: &mut AssetManager,
+    ) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let font = match font(assets) {
+            Ok(font) => font,
+            Err(e) => {
+                eprintln!("Failed to draw overlay: {}", e);
+                self.lines.clear();
+                return;
+            }
+        };
+
+        let mut vertices = Vec::new();
+        let mut indices: Vec<u16> = Vec::new();
+        let to_clip =
+            |x: f32, y: f32| [x / width as f32 * 2.0 - 1.0, 1.0 - y / height as f32 * 2.0];
+        let cells = Self::CHARACTERS.chars().count() as u32 + 1;
+        let mut quad = |x: f32, y: f32, size: (f32, f32), cell: u32, cell_size: (u32, u32)| {
+            let left = (cell * Self::CELL_WIDTH) as f32 / (cells * Self::CELL_WIDTH) as f32;
+            let right = left + cell_size.0 as f32 / (cells * Self::CELL_WIDTH) as f32;
+            let bottom = cell_size.1 as f32 / Self::CELL_HEIGHT as f32;
+            let corners = [
+                (x, y, left, 0.0),
+                (x, y + size.1, left, bottom),
+                (x + size.0, y + size.1, right, bottom),
+                (x + size.0, y, right, 0.0),
+            ];
+
+            let first = vertices.len() as u16;
+            for &(x, y, u, v) in corners.iter() {
+                let [x, y] = to_clip(x, y);
+                vertices.push(Vertex {
+                    position: [x, y, 0.0],
+                    tex_coords: [u, v],
+                    normal: [0.0, 0.0, 1.0],
+                });
+            }
+            indices.extend_from_slice(&[
+                first,
+                first + 1,
+                first + 3,
+                first + 1,
+                first + 2,
+                first + 3,
+            ]);
+        };
+
+        let backdrop = cells - 1;
+        let glyph = (Self::CELL_WIDTH - 1, Self::CELL_HEIGHT - 1);
+        let glyph_size = (glyph.0 as f32 * Self::SCALE, glyph.1 as f32 * Self::SCALE);
+        for (x, y, text) in self.lines.drain(..) {
+            // A font pixel of padding all round
+            let length = text.chars().count() as f32;
+            quad(
+                x,
+                y,
+                (
+                    (length * Self::CELL_WIDTH as f32 + 1.0) * Self::SCALE,
+                    (Self::CELL_HEIGHT as f32 + 1.0) * Self::SCALE,
+                ),
+                backdrop,
+                (1, 1),
+            );
+
+            for (i, character) in text.chars().enumerate() {
+                let cell = Self::CHARACTERS
+                    .chars()
+                    .position(|c| c == character)
+                    .unwrap_or(0);
+                if cell == 0 {
+                    continue;
+                }
+                let x = x + (i as f32 * Self::CELL_WIDTH as f32 + 1.0) * Self::SCALE;
+                quad(x, y + Self::SCALE, glyph_size, cell as u32, glyph);
+            }
+        }
+
+        use wgpu::util::DeviceExt;
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        let (bind_group, _) = font.create_bind_group(device);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..));
+        render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+    }
+}
+
+fn font(assets: &mut AssetManager) -> Result<Arc<Texture>, String> {
+    assets.texture_with(Overlay::FONT_TEXTURE, TextureOptions::PIXEL_ART)
+}
+
+fn create_pipeline(
+    device: &Device,
+    layout: &wgpu::PipelineLayout,
+    assets: &mut AssetManager,
+    texture_format: wgpu::TextureFormat,
+) -> Result<wgpu::RenderPipeline, String> {
+    let vs_module = assets.shader("shaders/overlay.vert")?;
+    let fs_module = assets.shader("shaders/overlay.frag")?;
+
+    Ok(
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                ..Default::default()
+            }),
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: texture_format,
+                color_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[Vertex::descriptor()],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        }),
+    )
+}
@@ -0,0 +1,318 @@
+use super::shadow::{ShadowQuality, ShadowSettings};
+use nanoserde::DeRon;
+
+#[derive(DeRon)]
+struct GraphicsConfig {
+    present_mode: Option<String>,
+    msaa_samples: Option<u32>,
+    resolution_scale: Option<f32>,
+    backend: Option<String>,
+    power_preference: Option<String>,
+    shadows: Option<bool>,
+    shadow_quality: Option<String>,
+    shadow_resolution: Option<u32>,
+    shadow_extent: Option<f32>,
+}
+
+/// How the renderer picks its GPU and presents frames, read from [`GraphicsSettings::CONFIG_PATH`]
+/// and then overridden from the command line
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GraphicsSettings {
+    pub present_mode: wgpu::PresentMode,
+    /// Samples per pixel, 1 turns multisampling off
+    pub msaa_samples: u32,
+    /// The scene is rendered at the window size times this and scaled to fit the window
+    pub resolution_scale: f32,
+    pub backends: wgpu::BackendBit,
+    pub power_preference: wgpu::PowerPreference,
+    /// What the shadow map starts with, shadows can still be toggled while running
+    pub shadows: ShadowSettings,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Fifo,
+            msaa_samples: 1,
+            resolution_scale: 1.0,
+            backends: wgpu::BackendBit::PRIMARY,
+            power_preference: wgpu::PowerPreference::Default,
+            shadows: ShadowSettings::default(),
+        }
+    }
+}
+
+impl GraphicsSettings {
+    pub const CONFIG_PATH: &'static str = "graphics.ron";
+
+    const MSAA_SAMPLES: &'static [u32] = &[1, 2, 4, 8];
+    const MIN_RESOLUTION_SCALE: f32 = 0.25;
+    const MAX_RESOLUTION_SCALE: f32 = 2.0;
+    const MIN_SHADOW_RESOLUTION: u32 = 256;
+    const MAX_SHADOW_RESOLUTION: u32 = 8192;
+    const MIN_SHADOW_EXTENT: f32 = 8.0;
+    const MAX_SHADOW_EXTENT: f32 = 256.0;
+
+    /// Loads the defaults and replaces every setting given in the config file.
+    /// A missing file leaves the defaults untouched.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut settings = Self::default();
+
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(settings),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+
+        let config =
+            GraphicsConfig::deserialize_ron(&src).map_err(|e| format!("{}: {:?}", path, e))?;
+
+        if let Some(present_mode) = &config.present_mode {
+            settings
+                .set_present_mode(present_mode)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(msaa_samples) = config.msaa_samples {
+            settings
+                .set_msaa_samples(msaa_samples)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(resolution_scale) = config.resolution_scale {
+            settings
+                .set_resolution_scale(resolution_scale)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(backend) = &config.backend {
+            settings
+                .set_backend(backend)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(power_preference) = &config.power_preference {
+            settings
+                .set_power_preference(power_preference)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(shadows) = config.shadows {
+            settings.shadows.enabled = shadows;
+        }
+        if let Some(shadow_quality) = &config.shadow_quality {
+            settings
+                .set_shadow_quality(shadow_quality)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(shadow_resolution) = config.shadow_resolution {
+            settings
+                .set_shadow_resolution(shadow_resolution)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        if let Some(shadow_extent) = config.shadow_extent {
+            settings
+                .set_shadow_extent(shadow_extent)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Replaces the settings given on the command line as `--present-mode`, `--msaa`,
+    /// `--resolution-scale`, `--backend`, `--power-preference`, `--shadows`,
+    /// `--shadow-quality`, `--shadow-resolution` and `--shadow-extent`, each followed by a
+    /// value written the same way as in the config file
+    pub fn override_from_args(
+        &mut self,
+        arg_value: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), String> {
+        if let Some(present_mode) = arg_value("--present-mode") {
+            self.set_present_mode(&present_mode)
+                .map_err(|e| format!("--present-mode: {}", e))?;
+        }
+        if let Some(msaa_samples) = arg_value("--msaa") {
+            let msaa_samples = msaa_samples
+                .parse()
+                .map_err(|e| format!("--msaa: {}: {}", msaa_samples, e))?;
+            self.set_msaa_samples(msaa_samples)
+                .map_err(|e| format!("--msaa: {}", e))?;
+        }
+        if let Some(resolution_scale) = arg_value("--resolution-scale") {
+            let resolution_scale = resolution_scale
+                .parse()
+                .map_err(|e| format!("--resolution-scale: {}: {}", resolution_scale, e))?;
+            self.set_resolution_scale(resolution_scale)
+                .map_err(|e| format!("--resolution-scale: {}", e))?;
+        }
+        if let Some(backend) = arg_value("--backend") {
+            self.set_backend(&backend)
+                .map_err(|e| format!("--backend: {}", e))?;
+        }
+        if let Some(power_preference) = arg_value("--power-preference") {
+            self.set_power_preference(&power_preference)
+                .map_err(|e| format!("--power-preference: {}", e))?;
+        }
+        if let Some(shadows) = arg_value("--shadows") {
+            self.shadows.enabled = shadows
+                .parse()
+                .map_err(|e| format!("--shadows: {}: {}", shadows, e))?;
+        }
+        if let Some(shadow_quality) = arg_value("--shadow-quality") {
+            self.set_shadow_quality(&shadow_quality)
+                .map_err(|e| format!("--shadow-quality: {}", e))?;
+        }
+        if let Some(shadow_resolution) = arg_value("--shadow-resolution") {
+            let shadow_resolution = shadow_resolution
+                .parse()
+                .map_err(|e| format!("--shadow-resolution: {}: {}", shadow_resolution, e))?;
+            self.set_shadow_resolution(shadow_resolution)
+                .map_err(|e| format!("--shadow-resolution: {}", e))?;
+        }
+        if let Some(shadow_extent) = arg_value("--shadow-extent") {
+            let shadow_extent = shadow_extent
+                .parse()
+                .map_err(|e| format!("--shadow-extent: {}: {}", shadow_extent, e))?;
+            self.set_shadow_extent(shadow_extent)
+                .map_err(|e| format!("--shadow-extent: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn set_present_mode(&mut self, name: &str) -> Result<(), String> {
+        self.present_mode = match name {
+            "Fifo" => wgpu::PresentMode::Fifo,
+            "Mailbox" => wgpu::PresentMode::Mailbox,
+            "Immediate" => wgpu::PresentMode::Immediate,
+            _ => return Err(format!("unknown present mode `{}`", name)),
+        };
+        Ok(())
+    }
+
+    fn set_msaa_samples(&mut self, msaa_samples: u32) -> Result<(), String> {
+        if !Self::MSAA_SAMPLES.contains(&msaa_samples) {
+            return Err(format!(
+                "{} MSAA samples isn't one of {:?}",
+                msaa_samples,
+                Self::MSAA_SAMPLES
+            ));
+        }
+        self.msaa_samples = msaa_samples;
+        Ok(())
+    }
+
+    fn set_resolution_scale(&mut self, resolution_scale: f32) -> Result<(), String> {
+        if !(Self::MIN_RESOLUTION_SCALE..=Self::MAX_RESOLUTION_SCALE).contains(&resolution_scale) {
+            return Err(format!(
+                "resolution scale {} isn't between {} and {}",
+                resolution_scale,
+                Self::MIN_RESOLUTION_SCALE,
+                Self::MAX_RESOLUTION_SCALE
+            ));
+        }
+        self.resolution_scale = resolution_scale;
+        Ok(())
+    }
+
+    fn set_backend(&mut self, name: &str) -> Result<(), String> {
+        self.backends = match name {
+            "Primary" => wgpu::BackendBit::PRIMARY,
+            "Secondary" => wgpu::BackendBit::SECONDARY,
+            "Vulkan" => wgpu::BackendBit::VULKAN,
+            "Metal" => wgpu::BackendBit::METAL,
+            "Dx12" => wgpu::BackendBit::DX12,
+            "Dx11" => wgpu::BackendBit::DX11,
+            "Gl" => wgpu::BackendBit::GL,
+            _ => return Err(format!("unknown backend `{}`", name)),
+        };
+        Ok(())
+    }
+
+    fn set_power_preference(&mut self, name: &str) -> Result<(), String> {
+        self.power_preference = match name {
+            "Default" => wgpu::PowerPreference::Default,
+            "LowPower" => wgpu::PowerPreference::LowPower,
+            "HighPerformance" => wgpu::PowerPreference::HighPerformance,
+            _ => return Err(format!("unknown power preference `{}`", name)),
+        };
+        Ok(())
+    }
+
+    fn set_shadow_quality(&mut self, name: &str) -> Result<(), String> {
+        self.shadows.quality = ShadowQuality::from_name(name)
+            .ok_or_else(|| format!("unknown shadow quality `{}`", name))?;
+        Ok(())
+    }
+
+    fn set_shadow_resolution(&mut self, resolution: u32) -> Result<(), String> {
+        if !resolution.is_power_of_two()
+            || !(Self::MIN_SHADOW_RESOLUTION..=Self::MAX_SHADOW_RESOLUTION).contains(&resolution)
+        {
+            return Err(format!(
+                "shadow resolution {} isn't a power of two between {} and {}",
+                resolution,
+                Self::MIN_SHADOW_RESOLUTION,
+                Self::MAX_SHADOW_RESOLUTION
+            ));
+        }
+        self.shadows.resolution = resolution;
+        Ok(())
+    }
+
+    fn set_shadow_extent(&mut self, extent: f32) -> Result<(), String> {
+        if !(Self::MIN_SHADOW_EXTENT..=Self::MAX_SHADOW_EXTENT).contains(&extent) {
+            return Err(format!(
+                "shadow extent {} isn't between {} and {}",
+                extent,
+                Self::MIN_SHADOW_EXTENT,
+                Self::MAX_SHADOW_EXTENT
+            ));
+        }
+        self.shadows.extent = extent;
+        Ok(())
+    }
+
+    /// Size of the targets the scene is rendered into for a window of `size`
+    pub fn scaled_size(&self, size: winit::dpi::PhysicalSize<u32>) -> (u32, u32) {
+        let scale = |length: u32| ((length as f32 * self.resolution_scale).round() as u32).max(1);
+        (scale(size.width), scale(size.height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_config_loads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../graphics.ron");
+        assert_eq!(
+            GraphicsSettings::load(path).unwrap(),
+            GraphicsSettings::default()
+        );
+    }
+
+    #[test]
+    fn shadows_are_overridden_from_args() {
+        let args = |name: &str| match name {
+            "--shadows" => Some("false".to_string()),
+            "--shadow-quality" => Some("High".to_string()),
+            "--shadow-resolution" => Some("1024".to_string()),
+            "--shadow-extent" => Some("32".to_string()),
+            _ => None,
+        };
+        let mut settings = GraphicsSettings::default();
+        settings.override_from_args(args).unwrap();
+        assert_eq!(
+            settings.shadows,
+            ShadowSettings {
+                enabled: false,
+                quality: ShadowQuality::High,
+                resolution: 1024,
+                extent: 32.0,
+            }
+        );
+
+        let mut settings = GraphicsSettings::default();
+        let odd_resolution = |name: &str| match name {
+            "--shadow-resolution" => Some("1000".to_string()),
+            _ => None,
+        };
+        assert!(settings.override_from_args(odd_resolution).is_err());
+    }
+}
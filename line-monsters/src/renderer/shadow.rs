@@ -0,0 +1,374 @@
+use super::spritebatch;
+use super::texture::Texture;
+use super::{Light, Vertex};
+use crate::camera::Camera;
+use std::sync::Arc;
+use ultraviolet::{Mat4, Vec3};
+use wgpu::Device;
+
+/// How many shadow map texels are averaged per pixel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShadowQuality {
+    /// A single comparison, filtered by the sampler only
+    Low,
+    /// 3x3 PCF
+    Medium,
+    /// 5x5 PCF
+    High,
+}
+
+impl ShadowQuality {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Low" => Some(ShadowQuality::Low),
+            "Medium" => Some(ShadowQuality::Medium),
+            "High" => Some(ShadowQuality::High),
+            _ => None,
+        }
+    }
+
+    fn pcf_radius(&self) -> i32 {
+        match self {
+            ShadowQuality::Low => 0,
+            ShadowQuality::Medium => 1,
+            ShadowQuality::High => 2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub quality: ShadowQuality,
+    /// Width and height of the shadow map in texels
+    pub resolution: u32,
+    /// Half the width of the area around the camera's focus that casts shadows, in tiles
+    pub extent: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quality: ShadowQuality::Medium,
+            resolution: 2048,
+            extent: 48.0,
+        }
+    }
+}
+
+unsafe impl bytemuck::Pod for ShadowUniforms {}
+unsafe impl bytemuck::Zeroable for ShadowUniforms {}
+
+/// Laid out to match the std140 `Shadow` block in the shaders
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct ShadowUniforms {
+    light_view_proj: Mat4,
+    enabled: f32,
+    pcf_radius: i32,
+    texel_size: f32,
+    _padding: f32,
+}
+
+/// Renders the terrain from the light into a depth texture and provides the bind
+/// group the main pass samples it through
+pub struct ShadowMap {
+    settings: ShadowSettings,
+
+    depth_texture: Arc<Texture>,
+
+    uniforms: ShadowUniforms,
+    uniform_buffer: wgpu::Buffer,
+
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    light_bind_group: wgpu::BindGroup,
+
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ShadowMap {
+    /// Distance from the camera's focus to the light's eye
+    const LIGHT_DISTANCE: f32 = 150.0;
+
+    pub fn new(device: &Device, settings: ShadowSettings, vs_module: &wgpu::ShaderModule) -> Self {
+        let uniforms = ShadowUniforms {
+            light_view_proj: Mat4::identity(),
+            enabled: 0.0,
+            pcf_radius: 0,
+            texel_size: 0.0,
+            _padding: 0.0,
+        };
+
+        use wgpu::util::DeviceExt;
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("shadow_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        // Shadow pass, only needs the light's view projection
+        let light_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("shadow_light_bind_group_layout"),
+            });
+
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &light_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+            }],
+            label: Some("shadow_light_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&light_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, vs_module);
+
+        // Main pass, samples the shadow map
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: true },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("shadow_bind_group_layout"),
+        });
+
+        let depth_texture = Self::create_depth_texture(device, &settings);
+        let bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &depth_texture, &uniform_buffer);
+
+        Self {
+            settings,
+
+            depth_texture,
+
+            uniforms,
+            uniform_buffer,
+
+            pipeline_layout,
+            pipeline,
+            light_bind_group,
+
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    fn create_pipeline(
+        device: &Device,
+        layout: &wgpu::PipelineLayout,
+        vs_module: &wgpu::ShaderModule,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: None,
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                // Pushes the stored depth back to stop surfaces shadowing themselves
+                depth_bias: 2,
+                depth_bias_slope_scale: 2.0,
+                depth_bias_clamp: 0.0,
+                clamp_depth: false,
+            }),
+            color_states: &[],
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilStateDescriptor::default(),
+            }),
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[Vertex::descriptor()],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        })
+    }
+
+    /// Rebuilds the pipeline after the shader was reloaded
+    pub fn set_shader(&mut self, device: &Device, vs_module: &wgpu::ShaderModule) {
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, vs_module);
+    }
+
+    /// A disabled shadow map keeps a 1x1 texture so the main pass always has something bound
+    fn create_depth_texture(device: &Device, settings: &ShadowSettings) -> Arc<Texture> {
+        let resolution = if settings.enabled {
+            settings.resolution
+        } else {
+            1
+        };
+        Texture::create_depth_texture(device, resolution, resolution, 1, "shadow_texture")
+    }
+
+    fn create_bind_group(
+        device: &Device,
+        layout: &wgpu::BindGroupLayout,
+        depth_texture: &Texture,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&depth_texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+            ],
+            label: Some("shadow_bind_group"),
+        })
+    }
+
+    pub fn settings(&self) -> ShadowSettings {
+        self.settings
+    }
+
+    /// Recreates the shadow map if the resolution or enabled state changed
+    pub fn set_settings(&mut self, device: &Device, settings: ShadowSettings) {
+        let recreate = settings.enabled != self.settings.enabled
+            || settings.resolution != self.settings.resolution;
+        self.settings = settings;
+
+        if recreate {
+            self.depth_texture = Self::create_depth_texture(device, &self.settings);
+            self.bind_group = Self::create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.depth_texture,
+                &self.uniform_buffer,
+            );
+        }
+    }
+
+    /// An orthographic view from the light, centred on where the camera looks at the ground
+    fn light_view_proj(&self, light: &Light, camera: &Camera) -> Mat4 {
+        let direction = Vec3::from(light.direction).normalized();
+
+        let t = if camera.direction.y < 0.0 {
+            -camera.eye.y / camera.direction.y
+        } else {
+            0.0
+        };
+        let focus = camera.eye + camera.direction * t;
+
+        let eye = focus - direction * Self::LIGHT_DISTANCE;
+        let up = if direction.y.abs() > 0.99 {
+            Vec3::unit_z()
+        } else {
+            Vec3::unit_y()
+        };
+        let view = Mat4::look_at(eye, focus, up);
+
+        let extent = self.settings.extent;
+        let proj = ultraviolet::projection::rh_yup::orthographic_wgpu_dx(
+            -extent,
+            extent,
+            -extent,
+            extent,
+            0.1,
+            Self::LIGHT_DISTANCE * 2.0,
+        );
+
+        proj * view
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, light: &Light, camera: &Camera) {
+        self.uniforms.light_view_proj = self.light_view_proj(light, camera);
+        self.uniforms.enabled = if self.settings.enabled { 1.0 } else { 0.0 };
+        self.uniforms.pcf_radius = self.settings.quality.pcf_radius();
+        self.uniforms.texel_size = 1.0 / self.depth_texture.size.width as f32;
+
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
+        );
+    }
+
+    /// Records the shadow pass, does nothing while shadows are disabled.
+    /// Only opaque batches cast shadows, the pass has no fragment stage to alpha test with.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, batches: &[spritebatch::Batch]) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        shadow_pass.set_pipeline(&self.pipeline);
+        shadow_pass.set_bind_group(0, &self.light_bind_group, &[]);
+
+        for batch in batches
+            .iter()
+            .filter(|batch| batch.mode == spritebatch::BlendMode::Opaque)
+        {
+            let spritebatch::IndexBuffer(index_buffer, num_indices) = &batch.index_buffer;
+            shadow_pass.set_vertex_buffer(0, batch.vertex_buffer.0.slice(..));
+            shadow_pass.set_index_buffer(index_buffer.slice(..));
+            shadow_pass.draw_indexed(0..*num_indices, 0, 0..1);
+        }
+    }
+}
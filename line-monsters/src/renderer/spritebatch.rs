@@ -1,23 +1,125 @@
 use super::texture::Texture;
 use super::Vertex;
+use crate::camera::Camera;
 use std::sync::Arc;
-use ultraviolet::{Rotor3, Vec3};
+use ultraviolet::{Rotor3, Vec2, Vec3};
 use wgpu::Device;
 
 pub struct IndexBuffer(pub wgpu::Buffer, pub u32);
 pub struct VertexBuffer(pub wgpu::Buffer);
 
+/// Which render pipeline a draw goes through
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Alpha is ignored
+    Opaque,
+    /// Pixels with alpha below one half are discarded, for foliage and fences
+    Cutout,
+    /// Alpha blended and drawn back to front after everything else, for water and overlays
+    Translucent,
+}
+
+pub struct Batch {
+    pub texture: Arc<Texture>,
+    pub mode: BlendMode,
+    pub vertex_buffer: VertexBuffer,
+    pub index_buffer: IndexBuffer,
+}
+
+/// How a sprite turns to face the camera
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Billboard {
+    /// Always parallel to the screen
+    Camera,
+    /// Only rotates around the Y axis so it stays upright, for characters standing on the ground
+    YLocked,
+}
+
+/// A region of a texture in texture coordinates, `0.0..=1.0` on both axes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl UvRect {
+    pub const FULL: UvRect = UvRect {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// A rect in pixels of `texture`, for picking a frame out of a sprite sheet
+    pub fn from_pixels(texture: &Texture, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let texture_width = texture.size.width as f32;
+        let texture_height = texture.size.height as f32;
+        Self {
+            x: x as f32 / texture_width,
+            y: y as f32 / texture_height,
+            width: width as f32 / texture_width,
+            height: height as f32 / texture_height,
+        }
+    }
+}
+
+/// A textured quad drawn at a point in the world, see [`Spritebatch::draw_sprite`]
+#[derive(Copy, Clone, Debug)]
+pub struct Sprite {
+    pub position: Vec3,
+    /// Width and height in world units
+    pub size: Vec2,
+    /// The point of the sprite placed at `position`, `(0.0, 0.0)` is the top left
+    /// and `(1.0, 1.0)` the bottom right
+    pub anchor: Vec2,
+    pub uv: UvRect,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub billboard: Billboard,
+}
+
+impl Sprite {
+    /// An upright sprite standing on `position`, showing the whole texture
+    pub fn new(position: Vec3, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            anchor: Vec2::new(0.5, 1.0),
+            uv: UvRect::FULL,
+            flip_x: false,
+            flip_y: false,
+            billboard: Billboard::YLocked,
+        }
+    }
+}
+
+/// Translucent draws are kept apart until [`Spritebatch::get_buffer`] knows where the eye is
+struct TranslucentDraw {
+    texture: Arc<Texture>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    centre: Vec3,
+}
+
 pub struct Spritebatch {
     device: Arc<Device>,
 
-    pub buffers: Vec<(Arc<Texture>, VertexBuffer, IndexBuffer)>,
+    pub buffers: Vec<Batch>,
 
     current_texture: Option<Arc<Texture>>,
+    current_mode: BlendMode,
     vertices: Vec<Vertex>,
     indices: Vec<u16>,
+
+    translucent: Vec<TranslucentDraw>,
 }
 
 impl Spritebatch {
+    /// Vertices are indexed with `u16`, so a batch holds at most this many
+    const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
     pub fn new(device: Arc<Device>) -> Self {
         Self {
             device,
@@ -25,27 +127,25 @@ impl Spritebatch {
             buffers: Vec::new(),
 
             current_texture: None,
+            current_mode: BlendMode::Opaque,
 
             vertices: Vec::with_capacity(128),
             indices: Vec::with_capacity(128),
+
+            translucent: Vec::new(),
         }
     }
 
     /// Plane is normalized in the draw call
-    pub fn draw(&mut self, position: Vec3, mut plane: Vec3, texture: Arc<Texture>) {
-        assert!(self.vertices.len() + 4 <= u16::MAX as usize);
-        assert!(self.indices.len() + 6 <= u32::MAX as usize);
-
+    pub fn draw(
+        &mut self,
+        position: Vec3,
+        mut plane: Vec3,
+        texture: Arc<Texture>,
+        mode: BlendMode,
+    ) {
         plane.normalize();
 
-        if let Some(cur_texture) = &self.current_texture {
-            if Arc::ptr_eq(&cur_texture, &texture) == false {
-                self.flush_to_buffer(Some(Arc::clone(&texture)));
-            }
-        } else {
-            self.current_texture = Some(Arc::clone(&texture));
-        }
-
         let rotor = Rotor3::from_rotation_between(Vec3::unit_y(), plane);
 
         let top_right = rotor * Vec3::unit_x();
@@ -60,18 +160,102 @@ impl Spritebatch {
             Vertex {
                 position: position.into(),
                 tex_coords: [0.0, 0.0],
+                normal: plane.into(),
             },
             Vertex {
                 position: (position + bottom_left).into(),
                 tex_coords: [0.0, 1.0],
+                normal: plane.into(),
             },
             Vertex {
                 position: (position + bottom_right).into(),
                 tex_coords: [1.0, 1.0],
+                normal: plane.into(),
             },
             Vertex {
                 position: (position + top_right).into(),
                 tex_coords: [1.0, 0.0],
+                normal: plane.into(),
+            },
+        ];
+
+        #[rustfmt::skip]
+        let indices: [u16; 6] = [
+            0, 1, 3,
+            1, 2, 3,
+        ];
+
+        self.push_verts(&vertices, &indices, texture, mode);
+    }
+
+    /// Draws a quad facing `camera`. Sprites take the normal of the ground they stand on
+    /// so they are lit the same as the tile under them rather than going dark when the
+    /// light is behind them.
+    pub fn draw_sprite(
+        &mut self,
+        sprite: &Sprite,
+        camera: &Camera,
+        texture: Arc<Texture>,
+        mode: BlendMode,
+    ) {
+        let (right, up) = match sprite.billboard {
+            Billboard::Camera => {
+                let right = camera.direction.cross(camera.up).normalized();
+                let up = right.cross(camera.direction).normalized();
+                (right, up)
+            }
+            Billboard::YLocked => {
+                let forward = Vec3::new(camera.direction.x, 0.0, camera.direction.z);
+                // Looking straight down leaves no horizontal direction to face
+                let forward = if forward.mag_sq() > 0.0001 {
+                    forward.normalized()
+                } else {
+                    -Vec3::unit_z()
+                };
+                (forward.cross(Vec3::unit_y()), Vec3::unit_y())
+            }
+        };
+
+        let right = right * sprite.size.x;
+        let up = up * sprite.size.y;
+        let top_left = sprite.position - right * sprite.anchor.x + up * sprite.anchor.y;
+
+        let UvRect {
+            x: mut left,
+            y: mut top,
+            width,
+            height,
+        } = sprite.uv;
+        let mut right_uv = left + width;
+        let mut bottom = top + height;
+        if sprite.flip_x {
+            std::mem::swap(&mut left, &mut right_uv);
+        }
+        if sprite.flip_y {
+            std::mem::swap(&mut top, &mut bottom);
+        }
+
+        let normal = [0.0, 1.0, 0.0];
+        let vertices: [Vertex; 4] = [
+            Vertex {
+                position: top_left.into(),
+                tex_coords: [left, top],
+                normal,
+            },
+            Vertex {
+                position: (top_left - up).into(),
+                tex_coords: [left, bottom],
+                normal,
+            },
+            Vertex {
+                position: (top_left - up + right).into(),
+                tex_coords: [right_uv, bottom],
+                normal,
+            },
+            Vertex {
+                position: (top_left + right).into(),
+                tex_coords: [right_uv, top],
+                normal,
             },
         ];
 
@@ -81,20 +265,57 @@ impl Spritebatch {
             1, 2, 3,
         ];
 
-        self.push_verts(&vertices, &indices, texture);
+        self.push_verts(&vertices, &indices, texture, mode);
+    }
+
+    pub fn push_verts(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        texture: Arc<Texture>,
+        mode: BlendMode,
+    ) {
+        if mode == BlendMode::Translucent {
+            let sum = vertices.iter().fold(Vec3::zero(), |sum, vertex| {
+                sum + Vec3::from(vertex.position)
+            });
+            let centre = sum / vertices.len().max(1) as f32;
+
+            self.translucent.push(TranslucentDraw {
+                texture,
+                vertices: vertices.to_vec(),
+                indices: indices.to_vec(),
+                centre,
+            });
+            return;
+        }
+
+        self.push_to_batch(vertices, indices, texture, mode);
     }
 
-    pub fn push_verts(&mut self, vertices: &[Vertex], indices: &[u16], texture: Arc<Texture>) {
-        assert!(self.vertices.len() + vertices.len() <= u16::MAX as usize);
-        assert!(self.indices.len() + indices.len() <= u32::MAX as usize);
+    fn push_to_batch(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u16],
+        texture: Arc<Texture>,
+        mode: BlendMode,
+    ) {
+        assert!(
+            vertices.len() <= Self::MAX_VERTICES,
+            "{} vertices don't fit in one batch",
+            vertices.len()
+        );
 
+        // A full batch is flushed like a change of texture and the draw starts a new one
+        let full = self.vertices.len() + vertices.len() > Self::MAX_VERTICES;
         if let Some(cur_texture) = &self.current_texture {
-            if Arc::ptr_eq(&cur_texture, &texture) == false {
+            if full || !Arc::ptr_eq(cur_texture, &texture) || self.current_mode != mode {
                 self.flush_to_buffer(Some(Arc::clone(&texture)));
             }
         } else {
             self.current_texture = Some(Arc::clone(&texture));
         }
+        self.current_mode = mode;
 
         let cur_vert_len = self.vertices.len() as u16;
         self.indices
@@ -127,19 +348,39 @@ impl Spritebatch {
             });
         let index_buffer = IndexBuffer(index_buffer, self.indices.len() as u32);
 
-        self.buffers.push((
-            self.current_texture.take().unwrap(),
+        self.buffers.push(Batch {
+            texture: self.current_texture.take().unwrap(),
+            mode: self.current_mode,
             vertex_buffer,
             index_buffer,
-        ));
+        });
 
         self.current_texture = new_texture;
         self.vertices.clear();
         self.indices.clear();
     }
 
-    pub fn get_buffer(&mut self) -> Vec<(Arc<Texture>, VertexBuffer, IndexBuffer)> {
+    /// Opaque and cutout batches come first in the order they were drawn,
+    /// followed by translucent draws sorted back to front from `eye`
+    pub fn get_buffer(&mut self, eye: Vec3) -> Vec<Batch> {
         self.flush_to_buffer(None);
+
+        let mut translucent: Vec<_> = self.translucent.drain(..).collect();
+        translucent.sort_by(|a, b| {
+            let a = (a.centre - eye).mag_sq();
+            let b = (b.centre - eye).mag_sq();
+            b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for draw in translucent {
+            self.push_to_batch(
+                &draw.vertices,
+                &draw.indices,
+                draw.texture,
+                BlendMode::Translucent,
+            );
+        }
+        self.flush_to_buffer(None);
+
         let buffer = self.buffers.drain(..).collect();
         buffer
     }
@@ -1,13 +1,47 @@
 use std::sync::Arc;
 
+use super::material::TextureAnimation;
 use image::GenericImageView;
 use wgpu::Device;
 
+/// How a texture is sampled, chosen when it is loaded
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Used for magnification, minification and between mip levels
+    pub filter: wgpu::FilterMode,
+    /// Generates the full chain of mip levels on load
+    pub mipmaps: bool,
+    pub animation: TextureAnimation,
+}
+
+impl TextureOptions {
+    /// Nearest filtering without mipmaps, keeps pixel art crisp
+    pub const PIXEL_ART: TextureOptions = TextureOptions {
+        filter: wgpu::FilterMode::Nearest,
+        mipmaps: false,
+        animation: TextureAnimation::Static,
+    };
+
+    /// Linear filtering with mipmaps, for UI and anything that should scale smoothly
+    pub const SMOOTH: TextureOptions = TextureOptions {
+        filter: wgpu::FilterMode::Linear,
+        mipmaps: true,
+        animation: TextureAnimation::Static,
+    };
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self::PIXEL_ART
+    }
+}
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
     pub size: wgpu::Extent3d,
+    pub animation: TextureAnimation,
 }
 
 impl Texture {
@@ -16,20 +50,39 @@ impl Texture {
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: &str,
-    ) -> Arc<Self> {
-        let img = image::load_from_memory(bytes).unwrap();
-        Self::from_image(device, queue, &img, Some(label))
+    ) -> Result<Arc<Self>, String> {
+        Self::from_bytes_with(device, queue, bytes, label, TextureOptions::default())
+    }
+
+    /// Decodes any image format and color type the `image` crate supports
+    pub fn from_bytes_with(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+        options: TextureOptions,
+    ) -> Result<Arc<Self>, String> {
+        let img = image::load_from_memory(bytes).map_err(|e| format!("{}: {}", label, e))?;
+        Ok(Self::from_image(device, queue, &img, Some(label), options))
     }
 
+    /// Converts `img` to 8 bit RGBA whatever its color type and bit depth
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
+        options: TextureOptions,
     ) -> Arc<Self> {
-        let rgba = img.as_rgba8().unwrap();
+        let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
+        let mip_level_count = if options.mipmaps {
+            32 - dimensions.0.max(dimensions.1).max(1).leading_zeros()
+        } else {
+            1
+        };
+
         let size = wgpu::Extent3d {
             width: dimensions.0,
             height: dimensions.1,
@@ -38,36 +91,64 @@ impl Texture {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
             usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
         });
 
-        queue.write_texture(
-            wgpu::TextureCopyView {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            rgba,
-            wgpu::TextureDataLayout {
-                offset: 0,
-                bytes_per_row: 4 * dimensions.0,
-                rows_per_image: dimensions.1,
-            },
-            size,
-        );
+        let resize_filter = match options.filter {
+            wgpu::FilterMode::Nearest => image::imageops::FilterType::Nearest,
+            wgpu::FilterMode::Linear => image::imageops::FilterType::Triangle,
+        };
+
+        for mip_level in 0..mip_level_count {
+            let width = (dimensions.0 >> mip_level).max(1);
+            let height = (dimensions.1 >> mip_level).max(1);
+
+            // Each level is scaled down from the full image rather than the level above,
+            // so errors don't add up
+            let level = if mip_level == 0 {
+                rgba.clone()
+            } else {
+                image::imageops::resize(&rgba, width, height, resize_filter)
+            };
+
+            queue.write_texture(
+                wgpu::TextureCopyView {
+                    texture: &texture,
+                    mip_level,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                &level,
+                wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: 4 * width,
+                    rows_per_image: height,
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth: 1,
+                },
+            );
+        }
+
+        // Scrolling samples past the edge of the texture and wraps around
+        let address_mode = match options.animation {
+            TextureAnimation::Scroll { .. } => wgpu::AddressMode::Repeat,
+            _ => wgpu::AddressMode::ClampToEdge,
+        };
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: options.filter,
+            min_filter: options.filter,
+            mipmap_filter: options.filter,
             ..Default::default()
         });
 
@@ -76,14 +157,18 @@ impl Texture {
             view,
             sampler,
             size,
+            animation: options.animation,
         })
     }
 
-    pub fn empty_texture(
+    /// A texture the scene is rendered into. Multisampled targets can only be resolved,
+    /// single sampled ones can also be bound and sampled like any other texture.
+    pub fn create_render_target(
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
         width: u32,
         height: u32,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
         label: &str,
     ) -> Arc<Self> {
         let size = wgpu::Extent3d {
@@ -91,33 +176,21 @@ impl Texture {
             height,
             depth: 1,
         };
+        let usage = if sample_count > 1 {
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT
+        } else {
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED
+        };
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT
-                | wgpu::TextureUsage::COPY_DST
-                | wgpu::TextureUsage::SAMPLED,
+            format,
+            usage,
         });
 
-        queue.write_texture(
-            wgpu::TextureCopyView {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &vec![0; 4 * width as usize * height as usize],
-            wgpu::TextureDataLayout {
-                offset: 0,
-                bytes_per_row: 4 * width,
-                rows_per_image: height,
-            },
-            size,
-        );
-
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -134,6 +207,7 @@ impl Texture {
             view,
             sampler,
             size,
+            animation: TextureAnimation::Static,
         })
     }
 }
@@ -145,6 +219,7 @@ impl Texture {
         device: &wgpu::Device,
         width: u32,
         height: u32,
+        sample_count: u32,
         label: &str,
     ) -> Arc<Self> {
         let size = wgpu::Extent3d {
@@ -152,14 +227,19 @@ impl Texture {
             height,
             depth: 1,
         };
+        let usage = if sample_count > 1 {
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT
+        } else {
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED
+        };
         let desc = wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            usage,
         };
         let texture = device.create_texture(&desc);
 
@@ -182,13 +262,14 @@ impl Texture {
             view,
             sampler,
             size,
+            animation: TextureAnimation::Static,
         })
     }
 }
 
 impl Texture {
     pub fn create_bind_group_layout(device: &Device) -> wgpu::BindGroupLayout {
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
@@ -208,8 +289,7 @@ impl Texture {
                 },
             ],
             label: Some("diffuse_bind_group_layout"),
-        });
-        bind_group_layout
+        })
     }
 
     pub fn create_bind_group(&self, device: &Device) -> (wgpu::BindGroup, wgpu::BindGroupLayout) {